@@ -0,0 +1,74 @@
+use crate::models::HexBytes;
+use log::error;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One line of the audit log. Only public material is recorded here:
+/// never add private keys, plaintext or transform keys to this struct.
+#[derive(Debug, PartialEq, Eq, Serialize, Clone)]
+pub struct AuditEntry {
+    // Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub operation: String,
+    // Recrypt public keys as x followed by y, ed25519 keys as-is
+    pub public_keys: Vec<HexBytes>,
+    pub status: String,
+}
+
+/// Append-only JSON lines log of cryptographic operations, written to
+/// the path given with `--audit-log`
+pub struct AuditLog {
+    file: Option<Mutex<File>>,
+}
+
+impl AuditLog {
+    pub fn disabled() -> Self {
+        AuditLog { file: None }
+    }
+
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| format!("Failed to open audit log {}: {:?}", path, err))?;
+        Ok(AuditLog {
+            file: Some(Mutex::new(file)),
+        })
+    }
+
+    /// Append an entry for `operation`. A failed write is reported but
+    /// does not fail the request that triggered it.
+    pub fn record<T, E>(&self, operation: &str, public_keys: &[&[u8]], result: &Result<T, E>) {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return,
+        };
+
+        let entry = AuditEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or(0),
+            operation: operation.to_string(),
+            public_keys: public_keys.iter().map(|key| HexBytes::from(*key)).collect(),
+            status: if result.is_ok() { "ok" } else { "error" }.to_string(),
+        };
+
+        let mut line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(err) => {
+                error!("Failed to serialize audit entry: {:?}", err);
+                return;
+            }
+        };
+        line.push('\n');
+
+        let mut file = file.lock().unwrap();
+        file.write_all(line.as_bytes())
+            .unwrap_or_else(|err| error!("Failed to write audit entry: {:?}", err));
+    }
+}
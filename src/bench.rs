@@ -0,0 +1,224 @@
+use crate::command_parser::BenchArgs;
+use crate::models::{Payload, SecretBytes};
+use crate::utils::http_client;
+use log::warn;
+use recrypt::api::{CryptoOps, KeyGenOps, Recrypt};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// Outcome of a `bench` run
+#[derive(Debug, Clone)]
+pub struct BenchSummary {
+    pub requests: usize,
+    pub failures: usize,
+    pub elapsed: Duration,
+    // Latencies of the successful requests, sorted ascending
+    pub latencies: Vec<Duration>,
+}
+
+impl BenchSummary {
+    pub fn requests_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        (self.requests - self.failures) as f64 / secs
+    }
+
+    /// Latency at percentile `p` (0-100), or zero when nothing succeeded
+    pub fn percentile(&self, p: usize) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::from_secs(0);
+        }
+        let index = (self.latencies.len() - 1) * p.min(100) / 100;
+        self.latencies[index]
+    }
+}
+
+impl fmt::Display for BenchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "requests:  {} ({} failed)", self.requests, self.failures)?;
+        writeln!(f, "elapsed:   {:?}", self.elapsed)?;
+        writeln!(f, "req/sec:   {:.2}", self.requests_per_sec())?;
+        write!(
+            f,
+            "latency:   p50 {:?}, p90 {:?}, p99 {:?}",
+            self.percentile(50),
+            self.percentile(90),
+            self.percentile(99)
+        )
+    }
+}
+
+/// Fire `args.requests` `/fetch-content` requests at `args.url`, at most
+/// `args.concurrency` at a time, and print a summary
+pub fn bench(args: BenchArgs) -> Result<(), String> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| format!("Failed to start runtime: {:?}", err))?;
+
+    let summary = runtime.block_on(run(&args))?;
+    println!("{}", summary);
+
+    Ok(())
+}
+
+async fn run(args: &BenchArgs) -> Result<BenchSummary, String> {
+    let client = http_client(args.api_key.as_deref())?;
+    let url = args.url.clone();
+    run_with(args, move |payload| {
+        let request = client.post(&url).json(&*payload).send();
+        async move {
+            match request.await {
+                Ok(response) => response.status().is_success(),
+                Err(err) => {
+                    warn!("Request failed: {}", err);
+                    false
+                }
+            }
+        }
+    })
+    .await
+}
+
+/// The request loop of `run`. `send` posts one payload and resolves to
+/// whether the request succeeded.
+async fn run_with<F, Fut>(args: &BenchArgs, send: F) -> Result<BenchSummary, String>
+where
+    F: Fn(Arc<Payload>) -> Fut,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    let payload = Arc::new(sample_payload()?);
+    let semaphore = Arc::new(Semaphore::new(args.concurrency));
+
+    let started = Instant::now();
+    let mut tasks = Vec::with_capacity(args.requests);
+    for _ in 0..args.requests {
+        let permit = Arc::clone(&semaphore)
+            .acquire_owned()
+            .await
+            .map_err(|err| format!("Failed to acquire request slot: {:?}", err))?;
+        let request = send(Arc::clone(&payload));
+
+        tasks.push(tokio::spawn(async move {
+            let _permit = permit;
+            let sent = Instant::now();
+            let ok = request.await;
+            (sent.elapsed(), ok)
+        }));
+    }
+
+    let mut latencies = Vec::with_capacity(args.requests);
+    let mut failures = 0;
+    for task in tasks {
+        let (latency, ok) = task
+            .await
+            .map_err(|err| format!("Request task failed: {:?}", err))?;
+        if ok {
+            latencies.push(latency);
+        } else {
+            failures += 1;
+        }
+    }
+    latencies.sort();
+
+    Ok(BenchSummary {
+        requests: args.requests,
+        failures,
+        elapsed: started.elapsed(),
+        latencies,
+    })
+}
+
+/// A `/fetch-content` payload with freshly generated owner and delegatee keys
+fn sample_payload() -> Result<Payload, String> {
+    let recrypt = Recrypt::new();
+    let (owner_private_key, owner_public_key) = recrypt
+        .generate_key_pair()
+        .map_err(|err| format!("Failed to generate owner keys: {:?}", err))?;
+    let (_, delegatee_public_key) = recrypt
+        .generate_key_pair()
+        .map_err(|err| format!("Failed to generate delegatee keys: {:?}", err))?;
+
+    Ok(Payload {
+        initial_private_key: SecretBytes::from(owner_private_key.bytes().as_slice()),
+        initial_public_key_x: Vec::from(owner_public_key.bytes_x_y().0.as_slice()),
+        initial_public_key_y: Vec::from(owner_public_key.bytes_x_y().1.as_slice()),
+        delegatee_public_key_x: Vec::from(delegatee_public_key.bytes_x_y().0.as_slice()),
+        delegatee_public_key_y: Vec::from(delegatee_public_key.bytes_x_y().1.as_slice()),
+        resource: recrypt.gen_plaintext().bytes().to_vec(),
+        transform_key: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_parser::ClientArgs;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::Client;
+    use std::sync::mpsc;
+    use std::thread;
+    use tokio::sync::oneshot;
+
+    type Request = (Arc<Payload>, oneshot::Sender<bool>);
+
+    /// Posts each received payload to `/fetch-content` of an in-process
+    /// Rocket, which lives on its own thread
+    fn local_rocket() -> mpsc::Sender<Request> {
+        let matches = crate::create_app!().get_matches_from(vec![
+            "app", "client", "--http", "--cid", "3", "--port", "5005",
+        ]);
+        let args = ClientArgs::new_with(matches.subcommand_matches("client").unwrap()).unwrap();
+        let (requests, received) = mpsc::channel::<Request>();
+        thread::spawn(move || {
+            let client = Client::new(crate::build_rocket(&args).unwrap()).unwrap();
+            for (payload, reply) in received {
+                let response = client
+                    .post("/fetch-content")
+                    .header(ContentType::JSON)
+                    .body(serde_json::to_string(&*payload).unwrap())
+                    .dispatch();
+                let _ = reply.send(response.status() == Status::Ok);
+            }
+        });
+        requests
+    }
+
+    #[test]
+    fn small_run_reports_nonzero_throughput() {
+        let args = BenchArgs {
+            url: "/fetch-content".to_string(),
+            requests: 8,
+            concurrency: 4,
+            api_key: None,
+        };
+        let requests = local_rocket();
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let summary = runtime
+            .block_on(run_with(&args, move |payload| {
+                let requests = requests.clone();
+                async move {
+                    let (reply, replied) = oneshot::channel();
+                    if requests.send((payload, reply)).is_err() {
+                        return false;
+                    }
+                    replied.await.unwrap_or(false)
+                }
+            }))
+            .unwrap();
+
+        assert_eq!(summary.requests, 8);
+        assert_eq!(summary.failures, 0);
+        assert_eq!(summary.latencies.len(), 8);
+        assert!(summary.requests_per_sec() > 0.0);
+    }
+}
@@ -1,43 +1,846 @@
-use clap::ArgMatches;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::models::KeySeed;
+use crate::utils::serialize_redacted;
+use clap::{ArgMatches, Shell};
+use log::LevelFilter;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+/// Number of vsock connections handled concurrently when
+/// `--max-vsock-connections` is not given
+pub const DEFAULT_MAX_VSOCK_CONNECTIONS: usize = 32;
+/// Seconds an accepted connection may go without sending data when
+/// `--recv-timeout-secs` is not given
+pub const DEFAULT_RECV_TIMEOUT_SECS: u64 = 30;
+/// Requests sent by `bench` when `--requests` is not given
+pub const DEFAULT_BENCH_REQUESTS: usize = 100;
+/// Requests `bench` keeps in flight when `--concurrency` is not given
+pub const DEFAULT_BENCH_CONCURRENCY: usize = 10;
+/// Address the client's HTTP API binds to when `--address` is not given.
+/// Loopback only; the Dockerfile passes `--address 0.0.0.0` to publish
+/// port 8000.
+pub const DEFAULT_HTTP_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+/// Origin allowed by CORS when `--cors-origin` is not given; `*` allows any
+pub const DEFAULT_CORS_ORIGIN: &str = "*";
+/// Largest JSON request body when `--max-body-bytes` is not given
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 1024 * 1024;
+/// vsock connection attempts when `--connect-retries` is not given
+pub const DEFAULT_CONNECT_RETRIES: usize = 5;
+/// Wait before the first reconnect when `--connect-backoff-ms` is not given
+pub const DEFAULT_CONNECT_BACKOFF_MS: u64 = 1000;
+/// Log level used when `--log-level` is not given
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+/// Environment variable the client reads its API key from when
+/// `--api-key` is not given, so the key stays out of `ps` output
+pub const API_KEY_ENV: &str = "PROXY_API_KEY";
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ServerArgs {
-    pub port: u32,
+    pub port: PortRange,
+    pub max_connections: usize,
+    // Seconds to wait for a connection before the server returns
+    pub accept_deadline: Option<u64>,
+    pub no_delay: bool,
+    // Seconds a connection may stall in a receive, 0 for no limit
+    pub recv_timeout_secs: u64,
 }
 
 impl ServerArgs {
-    pub fn new_with(args: &ArgMatches) -> Result<Self, String> {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        let config = parse_config(args)?;
         Ok(ServerArgs {
-            port: parse_port(args)?,
+            port: parse_port_range(args, &config)?,
+            max_connections: parse_count(
+                args,
+                "max-vsock-connections",
+                DEFAULT_MAX_VSOCK_CONNECTIONS,
+            )?,
+            accept_deadline: parse_number(args, "accept-deadline")?,
+            no_delay: args.is_present("no-delay"),
+            recv_timeout_secs: parse_number(args, "recv-timeout-secs")?
+                .unwrap_or(DEFAULT_RECV_TIMEOUT_SECS),
         })
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ClientArgs {
     pub cid: u32,
     pub port: u32,
+    // Address the HTTP API binds to with `--http`
+    pub address: IpAddr,
+    pub cors_origin: String,
+    pub include_freshness: bool,
+    pub enable_debug_endpoints: bool,
+    pub audit_log: Option<String>,
+    pub store: StoreBackend,
+    pub max_resources: Option<usize>,
+    pub store_eviction: StoreEviction,
+    pub allow_transform_key_disclosure: bool,
+    // Printed as "<redacted>" by `--print-config`
+    #[serde(serialize_with = "serialize_redacted")]
+    pub api_key: Option<String>,
+    pub max_body_bytes: u64,
+    // Seconds a request waits for recrypt before answering 503
+    pub crypto_timeout: Option<u64>,
+    // Serve the HTTP API instead of requesting keys over vsock
+    pub http: bool,
+    pub connect_retries: usize,
+    // Wait before the first reconnect, doubled after every attempt
+    pub connect_backoff_ms: u64,
 }
 
 impl ClientArgs {
-    pub fn new_with(args: &ArgMatches) -> Result<Self, String> {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        let config = parse_config(args)?;
+        let store = parse_store_backend(args)?;
+        let store_eviction = parse_store_eviction(args, &store)?;
         Ok(ClientArgs {
-            cid: parse_cid_client(args)?,
-            port: parse_port(args)?,
+            cid: parse_cid_client(args, &config)?,
+            port: parse_port(args, &config)?,
+            address: parse_address(args, &config)?,
+            cors_origin: args
+                .value_of("cors-origin")
+                .unwrap_or(DEFAULT_CORS_ORIGIN)
+                .to_string(),
+            include_freshness: args.is_present("include-freshness"),
+            enable_debug_endpoints: args.is_present("enable-debug-endpoints"),
+            audit_log: args.value_of("audit-log").map(String::from),
+            store,
+            max_resources: parse_optional_count(args, "max-resources")?,
+            store_eviction,
+            allow_transform_key_disclosure: args.is_present("allow-transform-key-disclosure"),
+            api_key: parse_api_key(args, std::env::var(API_KEY_ENV).ok(), &config),
+            max_body_bytes: parse_count(args, "max-body-bytes", DEFAULT_MAX_BODY_BYTES)?,
+            crypto_timeout: parse_optional_count(args, "crypto-timeout")?,
+            http: args.is_present("http"),
+            connect_retries: parse_count(args, "connect-retries", DEFAULT_CONNECT_RETRIES)?,
+            connect_backoff_ms: parse_number(args, "connect-backoff-ms")?
+                .unwrap_or(DEFAULT_CONNECT_BACKOFF_MS),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchArgs {
+    pub url: String,
+    pub requests: usize,
+    pub concurrency: usize,
+    #[serde(serialize_with = "serialize_redacted")]
+    pub api_key: Option<String>,
+}
+
+impl BenchArgs {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        Ok(BenchArgs {
+            url: parse_url(args)?,
+            requests: parse_count(args, "requests", DEFAULT_BENCH_REQUESTS)?,
+            concurrency: parse_count(args, "concurrency", DEFAULT_BENCH_CONCURRENCY)?,
+            api_key: args.value_of("api-key").map(String::from),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchSigningKeyArgs {
+    pub url: String,
+    pub output: String,
+}
+
+impl FetchSigningKeyArgs {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        Ok(FetchSigningKeyArgs {
+            url: parse_url(args)?,
+            output: parse_output(args)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifySigningKeyArgs {
+    pub key_file: String,
+    pub transformed_object: String,
+}
+
+impl VerifySigningKeyArgs {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        let key_file = args
+            .value_of("key-file")
+            .ok_or_else(|| missing("key-file"))?;
+        let transformed_object = args
+            .value_of("transformed-object")
+            .ok_or_else(|| missing("transformed-object"))?;
+        Ok(VerifySigningKeyArgs {
+            key_file: key_file.to_string(),
+            transformed_object: transformed_object.to_string(),
+        })
+    }
+}
+
+/// Output format of `convert-keys`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum KeyFormat {
+    Hex,
+    Bytes,
+}
+
+/// How log records are written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LogFormat {
+    Text,
+    // One JSON object per line with `level`, `target`, `msg` and `ts`
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConvertKeysArgs {
+    pub input: String,
+    pub output: String,
+    pub to: KeyFormat,
+}
+
+impl ConvertKeysArgs {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        let input = args.value_of("in").ok_or_else(|| missing("in"))?;
+        let output = args.value_of("out").ok_or_else(|| missing("out"))?;
+        let to = match args.value_of("to").ok_or_else(|| missing("to"))? {
+            "hex" => KeyFormat::Hex,
+            "bytes" => KeyFormat::Bytes,
+            _ => return Err(AppError::Parse("to must be hex or bytes".to_string())),
+        };
+        Ok(ConvertKeysArgs {
+            input: input.to_string(),
+            output: output.to_string(),
+            to,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ed25519KeygenArgs {
+    pub output: String,
+}
+
+impl Ed25519KeygenArgs {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        Ok(Ed25519KeygenArgs {
+            output: parse_output(args)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EncryptArgs {
+    // Payload JSON file, stdin when absent
+    pub input: Option<String>,
+    // File to encrypt in chunks instead of the payload's resource
+    pub plaintext_file: Option<String>,
+    // Transform key written by `transform-key`, reused instead of
+    // generating one from the payload's private key
+    pub transform_key_file: Option<String>,
+    // File to write the raw `TransformObject` protobuf to
+    pub dump_proto: Option<String>,
+}
+
+impl EncryptArgs {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        Ok(EncryptArgs {
+            input: args.value_of("in").map(String::from),
+            plaintext_file: args.value_of("plaintext-file").map(String::from),
+            transform_key_file: args.value_of("transform-key-file").map(String::from),
+            dump_proto: args.value_of("dump-proto").map(String::from),
         })
     }
 }
 
-fn parse_cid_client(args: &ArgMatches) -> Result<u32, String> {
-    let cid = args.value_of("cid").ok_or("Could not find cid argument")?;
-    cid.parse()
-        .map_err(|_err| "cid is not a number".to_string())
+// No Serialize, matching `KeygenArgs`: the input holds a private key
+#[derive(Debug, Clone)]
+pub struct TransformKeyArgs {
+    // `/transform-key` payload JSON file, stdin when absent
+    pub input: Option<String>,
+    pub output: String,
+}
+
+impl TransformKeyArgs {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        let output = args.value_of("out").ok_or_else(|| missing("out"))?;
+        Ok(TransformKeyArgs {
+            input: args.value_of("in").map(String::from),
+            output: output.to_string(),
+        })
+    }
+}
+
+// No Serialize: the seed is as secret as the key it generates
+#[derive(Debug, Clone)]
+pub struct KeygenArgs {
+    pub output: Option<String>,
+    pub seed: Option<KeySeed>,
+}
+
+impl KeygenArgs {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        Ok(KeygenArgs {
+            output: args.value_of("out").map(String::from),
+            seed: args
+                .value_of("seed")
+                .map(KeySeed::parse)
+                .transpose()
+                .map_err(AppError::Parse)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompletionsArgs {
+    pub shell: Shell,
+}
+
+impl CompletionsArgs {
+    pub fn new_with(args: &ArgMatches) -> Result<Self, AppError> {
+        let shell = args.value_of("shell").ok_or_else(|| missing("shell"))?;
+        Ok(CompletionsArgs {
+            shell: shell.parse().map_err(AppError::Parse)?,
+        })
+    }
+}
+
+/// `new_with_string_err` for each args struct: the pre-`AppError`
+/// signature, kept so callers matching on `String` errors still compile
+macro_rules! string_err_shims {
+    ($($args:ident),* $(,)?) => {
+        $(
+            impl $args {
+                #[deprecated(note = "use `new_with`, which returns `AppError`")]
+                pub fn new_with_string_err(args: &ArgMatches) -> Result<Self, String> {
+                    Self::new_with(args).map_err(|err| err.to_string())
+                }
+            }
+        )*
+    };
+}
+
+string_err_shims!(
+    ServerArgs,
+    ClientArgs,
+    BenchArgs,
+    FetchSigningKeyArgs,
+    VerifySigningKeyArgs,
+    ConvertKeysArgs,
+    Ed25519KeygenArgs,
+    EncryptArgs,
+    TransformKeyArgs,
+    KeygenArgs,
+    CompletionsArgs,
+);
+
+/// Where uploaded resources are kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum StoreBackend {
+    Memory,
+    // Directory holding one file per resource
+    Fs(String),
+}
+
+/// What a full memory store does with a new upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StoreEviction {
+    Reject,
+    // Drop the least recently inserted or read resource
+    Lru,
+}
+
+impl Default for StoreEviction {
+    fn default() -> Self {
+        StoreEviction::Reject
+    }
+}
+
+/// Inclusive range of ports the server tries to bind, in order.
+/// A single port is represented as a range where `start == end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl PortRange {
+    pub fn single(port: u32) -> Self {
+        PortRange {
+            start: port,
+            end: port,
+        }
+    }
+
+    /// Parse either a single port (`8000`), a range (`8000-8010`) or `0`
+    /// for a port chosen by the kernel
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        if value == "0" {
+            return Ok(PortRange::single(0));
+        }
+        match value.split_once('-') {
+            None => Ok(PortRange::single(parse_port_value(value)?)),
+            Some((start, end)) => {
+                let start = parse_port_value(start)?;
+                let end = parse_port_value(end)?;
+                if start > end {
+                    return Err(AppError::Parse(
+                        "port range start is greater than its end".to_string(),
+                    ));
+                }
+                Ok(PortRange { start, end })
+            }
+        }
+    }
+
+    pub fn ports(&self) -> impl Iterator<Item = u32> {
+        self.start..=self.end
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+impl Serialize for PortRange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
 }
 
-fn parse_port(args: &ArgMatches) -> Result<u32, String> {
+/// Print the resolved arguments as pretty JSON on stdout
+pub fn print_config<T: Serialize>(config: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(config)
+        .map_err(|err| format!("Failed to serialize config: {:?}", err))?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Reads the global `--log-format` flag from the root matches
+pub fn parse_log_format(args: &ArgMatches) -> Result<LogFormat, AppError> {
+    match args.value_of("log-format") {
+        Some("text") | None => Ok(LogFormat::Text),
+        Some("json") => Ok(LogFormat::Json),
+        Some(other) => Err(AppError::Parse(format!(
+            "log-format must be text or json (got {})",
+            other
+        ))),
+    }
+}
+
+/// Reads the global `--log-level` flag from the root matches
+pub fn parse_log_level(args: &ArgMatches) -> Result<LevelFilter, AppError> {
+    match args.value_of("log-level") {
+        Some("trace") => Ok(LevelFilter::Trace),
+        Some("debug") => Ok(LevelFilter::Debug),
+        Some("info") => Ok(LevelFilter::Info),
+        Some("warn") => Ok(LevelFilter::Warn),
+        Some("error") => Ok(LevelFilter::Error),
+        Some(other) => Err(AppError::Parse(format!(
+            "log-level must be one of trace, debug, info, warn, error (got {})",
+            other
+        ))),
+        None => Ok(DEFAULT_LOG_LEVEL),
+    }
+}
+
+/// Error for a flag that is neither given nor in the config file
+fn missing(name: &str) -> AppError {
+    AppError::Parse(format!("Could not find {} argument", name))
+}
+
+fn parse_config(args: &ArgMatches) -> Result<Config, AppError> {
+    match args.value_of("config") {
+        Some(path) => Config::from_file(path).map_err(AppError::Parse),
+        None => Ok(Config::default()),
+    }
+}
+
+fn parse_cid_client(args: &ArgMatches, config: &Config) -> Result<u32, AppError> {
+    match args.value_of("cid") {
+        Some(cid) => cid
+            .parse()
+            .map_err(|_err| AppError::Parse("cid is not a number".to_string())),
+        None => config.cid.ok_or_else(|| missing("cid")),
+    }
+}
+
+fn parse_port(args: &ArgMatches, config: &Config) -> Result<u32, AppError> {
+    let port = args
+        .value_of("port")
+        .or(config.port.as_deref())
+        .ok_or_else(|| missing("port"))?;
+    parse_port_value(port)
+}
+
+fn parse_output(args: &ArgMatches) -> Result<String, AppError> {
+    let output = args.value_of("output").ok_or_else(|| missing("output"))?;
+    Ok(output.to_string())
+}
+
+fn parse_url(args: &ArgMatches) -> Result<String, AppError> {
+    let url = args.value_of("url").ok_or_else(|| missing("url"))?;
+    Ok(url.to_string())
+}
+
+/// Parse a number, or `None` when the flag is absent
+fn parse_number<T: FromStr>(args: &ArgMatches, name: &str) -> Result<Option<T>, AppError> {
+    args.value_of(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_err| AppError::Parse(format!("{} is not a number", name)))
+        })
+        .transpose()
+}
+
+/// Parse a positive count, or `None` when the flag is absent
+fn parse_optional_count<T>(args: &ArgMatches, name: &str) -> Result<Option<T>, AppError>
+where
+    T: FromStr + PartialEq + From<u8>,
+{
+    match parse_number(args, name)? {
+        Some(count) if count == T::from(0) => {
+            Err(AppError::Parse(format!("{} must be greater than 0", name)))
+        }
+        count => Ok(count),
+    }
+}
+
+/// Parse a positive count, falling back to `default` when absent
+fn parse_count<T>(args: &ArgMatches, name: &str, default: T) -> Result<T, AppError>
+where
+    T: FromStr + PartialEq + From<u8>,
+{
+    Ok(parse_optional_count(args, name)?.unwrap_or(default))
+}
+
+fn parse_store_backend(args: &ArgMatches) -> Result<StoreBackend, AppError> {
+    match args.value_of("store-backend").unwrap_or("memory") {
+        "memory" => Ok(StoreBackend::Memory),
+        "fs" => {
+            let dir = args.value_of("store-dir").ok_or_else(|| {
+                AppError::Parse("store-dir is required with --store-backend fs".to_string())
+            })?;
+            Ok(StoreBackend::Fs(dir.to_string()))
+        }
+        _ => Err(AppError::Parse(
+            "store-backend must be memory or fs".to_string(),
+        )),
+    }
+}
+
+/// LRU needs access tracking, which only the memory store keeps
+fn parse_store_eviction(
+    args: &ArgMatches,
+    backend: &StoreBackend,
+) -> Result<StoreEviction, AppError> {
+    match args.value_of("store-eviction").unwrap_or("reject") {
+        "reject" => Ok(StoreEviction::Reject),
+        "lru" if *backend != StoreBackend::Memory => Err(AppError::Parse(
+            "store-eviction lru requires --store-backend memory".to_string(),
+        )),
+        "lru" => Ok(StoreEviction::Lru),
+        _ => Err(AppError::Parse(
+            "store-eviction must be reject or lru".to_string(),
+        )),
+    }
+}
+
+fn parse_address(args: &ArgMatches, config: &Config) -> Result<IpAddr, AppError> {
+    match args.value_of("address").or(config.address.as_deref()) {
+        Some(address) => address
+            .parse()
+            .map_err(|_err| AppError::Parse("address is not valid".to_string())),
+        None => Ok(DEFAULT_HTTP_ADDRESS),
+    }
+}
+
+/// `--api-key`, then the value of `API_KEY_ENV` (passed in as `env`),
+/// then the config file. An empty variable counts as unset.
+fn parse_api_key(args: &ArgMatches, env: Option<String>, config: &Config) -> Option<String> {
+    args.value_of("api-key")
+        .map(String::from)
+        .or_else(|| env.filter(|key| !key.is_empty()))
+        .or_else(|| config.api_key.clone())
+}
+
+fn parse_port_range(args: &ArgMatches, config: &Config) -> Result<PortRange, AppError> {
     let port = args
         .value_of("port")
-        .ok_or("Could not find port argument")?;
-    port.parse()
-        .map_err(|_err| "port is not a number".to_string())
+        .or(config.port.as_deref())
+        .ok_or_else(|| missing("port"))?;
+    PortRange::parse(port)
+}
+
+fn parse_port_value(port: &str) -> Result<u32, AppError> {
+    let port: u32 = port
+        .parse()
+        .map_err(|_err| AppError::Parse("port is not a number".to_string()))?;
+    if port == 0 || port > 65535 {
+        return Err(AppError::Parse(
+            "port must be between 1 and 65535".to_string(),
+        ));
+    }
+    Ok(port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{App, AppSettings, Arg, SubCommand};
+
+    fn client_matches(argv: &[&str]) -> ArgMatches<'static> {
+        let matches = crate::create_app!().get_matches_from(argv);
+        matches.subcommand_matches("client").unwrap().clone()
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn string_err_shim_keeps_the_display_message() {
+        let args = client_matches(&["app", "client", "--cid", "abc", "--port", "5005"]);
+        assert_eq!(
+            ClientArgs::new_with(&args).unwrap_err(),
+            AppError::Parse("cid is not a number".to_string())
+        );
+        assert_eq!(
+            ClientArgs::new_with_string_err(&args).unwrap_err(),
+            "cid is not a number"
+        );
+    }
+
+    #[test]
+    fn print_config_redacts_the_api_key() {
+        let args = client_matches(&[
+            "app",
+            "client",
+            "--cid",
+            "3",
+            "--port",
+            "5005",
+            "--api-key",
+            "hunter2",
+        ]);
+        let config = serde_json::to_value(ClientArgs::new_with(&args).unwrap()).unwrap();
+        assert_eq!(config["api_key"], "<redacted>");
+        assert!(!config.to_string().contains("hunter2"));
+
+        let args = client_matches(&["app", "client", "--cid", "3", "--port", "5005"]);
+        let config = serde_json::to_value(ClientArgs::new_with(&args).unwrap()).unwrap();
+        assert!(config["api_key"].is_null());
+    }
+
+    fn address_of(address: &str) -> Result<IpAddr, AppError> {
+        let args = client_matches(&[
+            "app",
+            "client",
+            "--cid",
+            "3",
+            "--port",
+            "5005",
+            "--address",
+            address,
+        ]);
+        ClientArgs::new_with(&args).map(|args| args.address)
+    }
+
+    #[test]
+    fn address_accepts_ipv4_and_ipv6() {
+        assert_eq!(
+            address_of("0.0.0.0").unwrap(),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        );
+        assert_eq!(address_of("::1").unwrap(), "::1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn address_rejects_garbage() {
+        assert_eq!(
+            address_of("not-an-address").unwrap_err(),
+            AppError::Parse("address is not valid".to_string())
+        );
+    }
+
+    fn client_port(port: &str) -> Result<u32, AppError> {
+        let args = client_matches(&["app", "client", "--cid", "3", "--port", port]);
+        ClientArgs::new_with(&args).map(|args| args.port)
+    }
+
+    #[test]
+    fn port_must_be_between_1_and_65535() {
+        let out_of_range = AppError::Parse("port must be between 1 and 65535".to_string());
+        assert_eq!(client_port("0").unwrap_err(), out_of_range);
+        assert_eq!(client_port("65536").unwrap_err(), out_of_range);
+        assert_eq!(client_port("70000").unwrap_err(), out_of_range);
+        assert_eq!(client_port("8080").unwrap(), 8080);
+        assert_eq!(
+            client_port("http").unwrap_err(),
+            AppError::Parse("port is not a number".to_string())
+        );
+    }
+
+    #[test]
+    fn log_level_flag_is_parsed_anywhere_on_the_command_line() {
+        let level = |argv: &[&str]| {
+            let mut full = vec!["app"];
+            full.extend_from_slice(argv);
+            full.extend_from_slice(&["--cid", "3", "--port", "5005"]);
+            let root = crate::create_app!().get_matches_from_safe(full)?;
+            Ok::<_, clap::Error>(parse_log_level(&root).unwrap())
+        };
+
+        assert_eq!(level(&["client"]).unwrap(), LevelFilter::Info);
+        assert_eq!(
+            level(&["--log-level", "debug", "client"]).unwrap(),
+            LevelFilter::Debug
+        );
+        assert_eq!(
+            level(&["client", "--log-level", "warn"]).unwrap(),
+            LevelFilter::Warn
+        );
+        assert!(level(&["--log-level", "loud", "client"]).is_err());
+    }
+
+    #[test]
+    fn lru_eviction_requires_the_memory_store() {
+        let args = client_matches(&[
+            "app",
+            "client",
+            "--cid",
+            "3",
+            "--port",
+            "5005",
+            "--store-backend",
+            "fs",
+            "--store-dir",
+            "/tmp/resources",
+            "--store-eviction",
+            "lru",
+        ]);
+        assert_eq!(
+            ClientArgs::new_with(&args).unwrap_err(),
+            AppError::Parse("store-eviction lru requires --store-backend memory".to_string())
+        );
+    }
+
+    #[test]
+    fn flags_take_precedence_over_the_config_file() {
+        let path = std::env::temp_dir().join(format!("config-{}.toml", std::process::id()));
+        std::fs::write(&path, "port = 6006\ncid = 4\napi_key = \"from-file\"\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let args = client_matches(&["app", "client", "--config", path, "--port", "7007"]);
+        let from_both = ClientArgs::new_with(&args).unwrap();
+        let args = client_matches(&["app", "client", "--config", path]);
+        let from_file = ClientArgs::new_with(&args).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(from_both.port, 7007);
+        assert_eq!(from_both.cid, 4);
+        assert_eq!(from_file.port, 6006);
+        assert_eq!(from_file.api_key.as_deref(), Some("from-file"));
+    }
+
+    #[test]
+    fn api_key_falls_back_to_the_environment() {
+        let config = Config {
+            api_key: Some("from-file".to_string()),
+            ..Config::default()
+        };
+        let without_flag = client_matches(&["app", "client", "--cid", "3", "--port", "5005"]);
+        let with_flag = client_matches(&[
+            "app",
+            "client",
+            "--cid",
+            "3",
+            "--port",
+            "5005",
+            "--api-key",
+            "from-flag",
+        ]);
+        let env = || Some("from-env".to_string());
+
+        assert_eq!(
+            parse_api_key(&without_flag, env(), &config).as_deref(),
+            Some("from-env")
+        );
+        assert_eq!(
+            parse_api_key(&with_flag, env(), &config).as_deref(),
+            Some("from-flag")
+        );
+        assert_eq!(
+            parse_api_key(&without_flag, Some(String::new()), &config).as_deref(),
+            Some("from-file")
+        );
+        assert_eq!(parse_api_key(&without_flag, None, &Config::default()), None);
+    }
+
+    #[test]
+    fn counts_must_be_positive_numbers() {
+        let client_with = |extra: &[&str]| {
+            let mut argv = vec!["app", "client", "--cid", "3", "--port", "5005"];
+            argv.extend_from_slice(extra);
+            ClientArgs::new_with(&client_matches(&argv))
+        };
+
+        let args = client_with(&[]).unwrap();
+        assert_eq!(args.max_body_bytes, DEFAULT_MAX_BODY_BYTES);
+        assert_eq!(args.max_resources, None);
+        assert_eq!(args.crypto_timeout, None);
+
+        let args = client_with(&["--max-resources", "3", "--crypto-timeout", "5"]).unwrap();
+        assert_eq!(args.max_resources, Some(3));
+        assert_eq!(args.crypto_timeout, Some(5));
+
+        assert_eq!(
+            client_with(&["--crypto-timeout", "0"]).unwrap_err(),
+            AppError::Parse("crypto-timeout must be greater than 0".to_string())
+        );
+        assert_eq!(
+            client_with(&["--max-body-bytes", "big"]).unwrap_err(),
+            AppError::Parse("max-body-bytes is not a number".to_string())
+        );
+    }
+
+    #[test]
+    fn recv_timeout_of_zero_disables_the_limit() {
+        let server_with = |extra: &[&str]| {
+            let mut argv = vec!["app", "server", "--port", "5005"];
+            argv.extend_from_slice(extra);
+            let matches = crate::create_app!().get_matches_from(argv);
+            ServerArgs::new_with(matches.subcommand_matches("server").unwrap())
+        };
+
+        let args = server_with(&[]).unwrap();
+        assert_eq!(args.recv_timeout_secs, DEFAULT_RECV_TIMEOUT_SECS);
+        assert_eq!(args.max_connections, DEFAULT_MAX_VSOCK_CONNECTIONS);
+        assert_eq!(
+            server_with(&["--recv-timeout-secs", "0"])
+                .unwrap()
+                .recv_timeout_secs,
+            0
+        );
+        assert_eq!(
+            server_with(&["--max-vsock-connections", "0"]).unwrap_err(),
+            AppError::Parse("max-vsock-connections must be greater than 0".to_string())
+        );
+    }
+
+    #[test]
+    fn address_defaults_to_loopback() {
+        let args = client_matches(&["app", "client", "--cid", "3", "--port", "5005"]);
+        assert_eq!(
+            ClientArgs::new_with(&args).unwrap().address,
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+    }
 }
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Deserializer};
+use std::fs;
+
+/// Settings loaded from `--config`. Every field is optional and a flag
+/// given on the command line takes precedence over the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // A single port or, for the server, a range such as "5005-5010"
+    #[serde(default, deserialize_with = "port_or_range")]
+    pub port: Option<String>,
+    pub cid: Option<u32>,
+    pub address: Option<String>,
+    pub api_key: Option<String>,
+}
+
+impl Config {
+    pub fn from_file(path: &str) -> Result<Config, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read config file {}: {:?}", path, err))?;
+        toml::from_str(&contents)
+            .map_err(|err| format!("Failed to parse config file {}: {}", path, err))
+    }
+}
+
+/// `port = 5005` or `port = "5005-5010"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PortOrRange {
+    Port(u32),
+    Range(String),
+}
+
+fn port_or_range<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(
+        Option::<PortOrRange>::deserialize(deserializer)?.map(|port| match port {
+            PortOrRange::Port(port) => port.to_string(),
+            PortOrRange::Range(range) => range,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_config_is_parsed() {
+        let config: Config = toml::from_str(
+            r#"
+            port = 5005
+            cid = 3
+            address = "127.0.0.1"
+            api_key = "from-file"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                port: Some("5005".to_string()),
+                cid: Some(3),
+                address: Some("127.0.0.1".to_string()),
+                api_key: Some("from-file".to_string()),
+            }
+        );
+
+        let config: Config = toml::from_str(r#"port = "5005-5010""#).unwrap();
+        assert_eq!(config.port.as_deref(), Some("5005-5010"));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<Config>("prot = 5005").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::models::{
+    HexBytes, TransformPublicKeyCollection, TransformedBlockResponse, TransformedObject,
+};
+use crate::proto::transform::{PublicKey as PPK, TransformBlock as TFB, TransformObject as TFO};
+use recrypt::api::PublicKey;
+use std::convert::TryFrom;
+
+// Conversions between the protobuf transform object and its JSON form,
+// `TransformedObject`. Protobuf to JSON never fails; the other way
+// checks that public keys are valid for the linked recrypt version.
+
+impl From<&PPK> for TransformPublicKeyCollection {
+    fn from(ppk: &PPK) -> Self {
+        TransformPublicKeyCollection {
+            public_key_x: HexBytes::from(ppk.x.as_slice()),
+            public_key_y: HexBytes::from(ppk.y.as_slice()),
+        }
+    }
+}
+
+impl TryFrom<&TransformPublicKeyCollection> for PPK {
+    type Error = String;
+
+    fn try_from(collection: &TransformPublicKeyCollection) -> Result<Self, Self::Error> {
+        // recrypt checks the coordinate lengths and that the point is on its curve
+        PublicKey::try_from(collection)?;
+        let mut ppk = PPK::new();
+        ppk.x = collection.public_key_x.0.clone();
+        ppk.y = collection.public_key_y.0.clone();
+        Ok(ppk)
+    }
+}
+
+impl From<&TFB> for TransformedBlockResponse {
+    fn from(tfb: &TFB) -> Self {
+        TransformedBlockResponse {
+            public_key: tfb.public_key.as_ref().map(Into::into).unwrap_or_default(),
+            encrypted_temp_key: HexBytes::from(tfb.encrypted_temp_key.as_slice()),
+            encrypted_random_transform_temp_key: HexBytes::from(
+                tfb.encrypted_random_transform_temp_key.as_slice(),
+            ),
+            random_transform_public_key: tfb
+                .random_transform_public_key
+                .as_ref()
+                .map(Into::into)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<&TransformedBlockResponse> for TFB {
+    type Error = String;
+
+    fn try_from(block: &TransformedBlockResponse) -> Result<Self, Self::Error> {
+        let mut tfb = TFB::new();
+        tfb.public_key = Some(PPK::try_from(&block.public_key)?).into();
+        tfb.encrypted_temp_key = block.encrypted_temp_key.0.clone();
+        tfb.random_transform_public_key =
+            Some(PPK::try_from(&block.random_transform_public_key)?).into();
+        tfb.encrypted_random_transform_temp_key =
+            block.encrypted_random_transform_temp_key.0.clone();
+        Ok(tfb)
+    }
+}
+
+impl From<&TFO> for TransformedObject {
+    fn from(tfo: &TFO) -> Self {
+        TransformedObject {
+            ephemeral_public_key: tfo
+                .ephemeral_public_key
+                .as_ref()
+                .map(Into::into)
+                .unwrap_or_default(),
+            encrypted_message: HexBytes::from(tfo.encrypted_message.as_slice()),
+            auth_hash: HexBytes::from(tfo.auth_hash.as_slice()),
+            transform_blocks: tfo.transform_blocks.iter().map(Into::into).collect(),
+            public_signing_key: HexBytes::from(tfo.public_signing_key.as_slice()),
+            ed25519_signature: HexBytes::from(tfo.ed25519_signature.as_slice()),
+            // proto3 leaves unset scalars at their zero value
+            timestamp: Some(tfo.timestamp).filter(|timestamp| *timestamp != 0),
+            nonce: Some(HexBytes::from(tfo.nonce.as_slice())).filter(|nonce| !nonce.is_empty()),
+        }
+    }
+}
+
+impl TryFrom<&TransformedObject> for TFO {
+    type Error = String;
+
+    fn try_from(object: &TransformedObject) -> Result<Self, Self::Error> {
+        let mut tfo = TFO::new();
+        tfo.ephemeral_public_key = Some(PPK::try_from(&object.ephemeral_public_key)?).into();
+        tfo.encrypted_message = object.encrypted_message.0.clone();
+        tfo.auth_hash = object.auth_hash.0.clone();
+        tfo.transform_blocks = object
+            .transform_blocks
+            .iter()
+            .map(TFB::try_from)
+            .collect::<Result<_, _>>()?;
+        tfo.public_signing_key = object.public_signing_key.0.clone();
+        tfo.ed25519_signature = object.ed25519_signature.0.clone();
+        tfo.timestamp = object.timestamp.unwrap_or(0);
+        tfo.nonce = object
+            .nonce
+            .as_ref()
+            .map(|nonce| nonce.0.clone())
+            .unwrap_or_default();
+        Ok(tfo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tfo_from_transformed;
+    use recrypt::api::{CryptoOps, Ed25519Ops, KeyGenOps, Recrypt};
+
+    fn transformed_tfo() -> TFO {
+        let recrypt = Recrypt::new();
+        let signing_keypair = recrypt.generate_ed25519_key_pair();
+        let (owner_private_key, owner_public_key) = recrypt.generate_key_pair().unwrap();
+        let (_, delegatee_public_key) = recrypt.generate_key_pair().unwrap();
+
+        let plaintext = recrypt.gen_plaintext();
+        let encrypted = recrypt
+            .encrypt(&plaintext, &owner_public_key, &signing_keypair)
+            .unwrap();
+        let transform_key = recrypt
+            .generate_transform_key(&owner_private_key, &delegatee_public_key, &signing_keypair)
+            .unwrap();
+        let transformed = recrypt
+            .transform(encrypted, transform_key, &signing_keypair)
+            .unwrap();
+        tfo_from_transformed(transformed).0
+    }
+
+    #[test]
+    fn transform_object_survives_json_round_trip() {
+        let mut tfo = transformed_tfo();
+        tfo.timestamp = 1_700_000_000;
+        tfo.nonce = vec![7; 16];
+
+        let json = serde_json::to_string(&TransformedObject::from(&tfo)).unwrap();
+        let object: TransformedObject = serde_json::from_str(&json).unwrap();
+        assert_eq!(TFO::try_from(&object).unwrap(), tfo);
+    }
+
+    #[test]
+    fn short_public_key_coordinate_is_rejected() {
+        let mut object = TransformedObject::from(&transformed_tfo());
+        object.ephemeral_public_key.public_key_x.0.pop();
+        assert!(TFO::try_from(&object).is_err());
+    }
+}
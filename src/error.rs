@@ -0,0 +1,51 @@
+use std::error::Error;
+use std::fmt;
+use std::num::ParseIntError;
+
+/// Error returned by the command line parser and the vsock protocol
+/// helpers. `Display` is the bare message, as the `String` errors these
+/// modules used to return.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    // A command line flag or config file value is missing or invalid
+    Parse(String),
+    // A socket call failed
+    Socket(String),
+    // A cryptographic operation failed
+    Crypto(String),
+    // The peer sent something the framing does not allow
+    Protocol(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::Parse(msg)
+            | AppError::Socket(msg)
+            | AppError::Crypto(msg)
+            | AppError::Protocol(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for AppError {}
+
+impl From<nix::Error> for AppError {
+    fn from(err: nix::Error) -> Self {
+        AppError::Socket(format!("{:?}", err))
+    }
+}
+
+impl From<ParseIntError> for AppError {
+    fn from(err: ParseIntError) -> Self {
+        AppError::Parse(err.to_string())
+    }
+}
+
+/// Lets `?` pass an `AppError` up through functions that still return
+/// `Result<_, String>`
+impl From<AppError> for String {
+    fn from(err: AppError) -> Self {
+        err.to_string()
+    }
+}
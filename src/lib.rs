@@ -1,50 +1,123 @@
 #![feature(proc_macro_hygiene, decl_macro)]
 
-#[macro_use] extern crate rocket;
+#[macro_use]
+extern crate rocket;
 
 extern crate ed25519_dalek;
 
+mod audit;
+pub mod bench;
 pub mod command_parser;
+pub mod config;
+mod convert;
+pub mod error;
+mod proto;
+pub mod proto_helpers;
 pub mod protocol_helpers;
+pub mod signing_key;
+mod store;
 pub mod utils;
-mod proto;
-use command_parser::{ClientArgs, ServerArgs};
-use protocol_helpers::{recv_loop, recv_u64, send_loop, send_u64};
-
+use audit::AuditLog;
+use command_parser::{
+    ClientArgs, ConvertKeysArgs, Ed25519KeygenArgs, EncryptArgs, KeyFormat, KeygenArgs, PortRange,
+    ServerArgs, TransformKeyArgs, DEFAULT_CORS_ORIGIN,
+};
+use protocol_helpers::{expect_exact, send_message, set_recv_timeout, MessageReader};
+use store::{open_store, ResourceStore};
+
+use ecies_ed25519::PUBLIC_KEY_LENGTH;
+use ed25519_dalek::Keypair;
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
 use nix::sys::socket::listen as listen_vsock;
-use nix::sys::socket::{accept, bind, connect, shutdown, socket};
+use nix::sys::socket::sockopt::TcpNoDelay;
+use nix::sys::socket::{accept, bind, connect, getsockname, setsockopt, shutdown, socket};
 use nix::sys::socket::{AddressFamily, Shutdown, SockAddr, SockFlag, SockType};
 use nix::unistd::close;
-use std::convert::TryInto;
-use std::os::unix::io::{AsRawFd, RawFd};
-use ed25519_dalek::Keypair;
 use rand::rngs::OsRng;
-use recrypt::api::{CryptoOps, Ed25519Ops, EncryptedValue, KeyGenOps, Plaintext, PrivateKey, PublicKey, Recrypt, TransformBlock};
-use rocket::Config;
-use rocket::config::{Environment, LoggingLevel};
-use rocket::http::Method;
-
+use rand::{Rng, RngCore};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use recrypt::api::{
+    AuthHash, CryptoOps, DefaultRng, Ed25519, Ed25519Ops, Ed25519Signature, EncryptedMessage,
+    EncryptedTempKey, EncryptedValue, KeyGenOps, Plaintext, PrivateKey, PublicKey,
+    PublicSigningKey, RandomBytes, Recrypt, RecryptErr, Sha256, SigningKeypair, TransformBlock,
+    TransformKey,
+};
+use recrypt::nonemptyvec::NonEmptyVec;
+use rocket::config::{Environment, Limits, LoggingLevel};
+use rocket::fairing::AdHoc;
+use rocket::http::uri::Origin;
+use rocket::http::{Accept, ContentType, MediaType, Method, RawStr, Status};
+use rocket::request::{self, FromRequest};
+use rocket::response::Content;
+use rocket::Outcome;
+use rocket::{Config, State};
+use rocket::{Data, Request, Response};
+use std::fmt;
+use std::io::Read;
+use std::os::raw::c_int;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use proto::transform::{PublicKey as PPK, TransformBlock as TFB, TransformObject as TFO};
-use protobuf;
 use protobuf::Message;
 
 mod models;
 
+pub use crate::models::Payload;
+use crate::models::{
+    ApiError, BinaryTransformedObjectResponse, ChunkedTransformResponse, CoordEndian,
+    CryptoDiagnostics, DecryptPayload, DecryptResponse, Ed25519Keys, EncryptedResponse,
+    HealthResponse, HexBytes, KeySeed, Keys, KeysHex, OutputEncoding, Payload,
+    ResourceDecryptPayload, SecretBytes, SigningKeyResponse, TransformKeyCollection,
+    TransformKeyPayload, TransformKeyResponse, TransformedObject, TransformedObjectResponse,
+    UploadResponse, VerifyPayload, VerifyResponse,
+};
+use crate::proto_helpers::{tfo_from_hex, write_proto_to_file};
+use crate::utils::{ct_eq, redact};
+use log::{debug, error, info, trace, warn};
 use rocket_contrib::json::Json;
-use rocket_cors::{AllowedOrigins, Cors, CorsOptions};
-use serde::{Serialize, Deserialize};
-use crate::models::{EncryptedResponse, Keys, Payload, TransformedBlockResponse, TransformedObject, TransformedObjectResponse, TransformPublicKeyCollection};
+use rocket_cors::{AllowedOrigins, CorsOptions};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
 
 extern crate rand;
 
 const VMADDR_CID_ANY: u32 = 0xFFFFFFFF;
-const BUF_MAX_LEN: usize = 32;
+// Port that asks the kernel to pick a free one on bind
+const VMADDR_PORT_ANY: u32 = 0xFFFFFFFF;
 // Maximum number of outstanding connections in the socket's
 // listen queue
 const BACKLOG: usize = 128;
-// Maximum number of connection attempts
-const MAX_CONNECTION_ATTEMPTS: usize = 5;
+// Longest wait between vsock connection attempts
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(300);
+// Maximum number of recrypt key generation attempts per request
+const MAX_KEYGEN_ATTEMPTS: usize = 3;
+// Field coordinates in a recrypt plaintext, and the encoded size of each
+const PLAINTEXT_COORDS: usize = 12;
+const PLAINTEXT_COORD_LEN: usize = 32;
+// Data bytes per plaintext in `chunk_into_plaintexts`
+const PLAINTEXT_CHUNK_LEN: usize = PLAINTEXT_COORDS * (PLAINTEXT_COORD_LEN - 1);
+// Length of the nonce embedded by `--include-freshness`
+const FRESHNESS_NONCE_LEN: usize = 16;
+// Bytes in the UUIDs given to uploaded resources
+const RESOURCE_ID_LEN: usize = 16;
+// Keep in sync with the recrypt dependency in Cargo.toml
+const RECRYPT_VERSION: &str = "0.13.1";
+// How often a waiting accept checks whether SIGTERM was received
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+// Upper bound on an ECIES-encrypted key half sent by `handle_connection`;
+// the real messages are under 100 bytes
+const MAX_KEY_MESSAGE_LEN: usize = 1024;
+
+// Set by the SIGTERM handler installed by `server`
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
 
 struct VsockSocket {
     socket_fd: RawFd,
@@ -58,9 +131,12 @@ impl VsockSocket {
 
 impl Drop for VsockSocket {
     fn drop(&mut self) {
-        shutdown(self.socket_fd, Shutdown::Both)
-            .unwrap_or_else(|e| eprintln!("Failed to shut socket down: {:?}", e));
-        close(self.socket_fd).unwrap_or_else(|e| eprintln!("Failed to close socket: {:?}", e));
+        // Listening sockets were never connected, so ENOTCONN is expected
+        match shutdown(self.socket_fd, Shutdown::Both) {
+            Ok(()) | Err(nix::Error::Sys(Errno::ENOTCONN)) => {}
+            Err(e) => error!("Failed to shut socket down: {:?}", e),
+        }
+        close(self.socket_fd).unwrap_or_else(|e| error!("Failed to close socket: {:?}", e));
     }
 }
 
@@ -70,12 +146,18 @@ impl AsRawFd for VsockSocket {
     }
 }
 
-/// Initiate a connection on an AF_VSOCK socket
-fn vsock_connect(cid: u32, port: u32) -> Result<VsockSocket, String> {
+/// Initiate a connection on an AF_VSOCK socket, making up to
+/// `max_attempts` attempts with exponential backoff from `base_backoff`
+fn vsock_connect(
+    cid: u32,
+    port: u32,
+    max_attempts: usize,
+    base_backoff: Duration,
+) -> Result<VsockSocket, String> {
     let sockaddr = SockAddr::new_vsock(cid, port);
     let mut err_msg = String::new();
 
-    for i in 0..MAX_CONNECTION_ATTEMPTS {
+    for i in 0..max_attempts {
         let vsocket = VsockSocket::new(
             socket(
                 AddressFamily::Vsock,
@@ -91,12 +173,43 @@ fn vsock_connect(cid: u32, port: u32) -> Result<VsockSocket, String> {
         }
 
         // Exponentially backoff before retrying to connect to the socket
-        std::thread::sleep(std::time::Duration::from_secs(1 << i));
+        if i + 1 < max_attempts {
+            std::thread::sleep(backoff_delay(
+                &mut rand::thread_rng(),
+                base_backoff,
+                i,
+                max_attempts,
+            ));
+        }
     }
 
     Err(err_msg)
 }
 
+/// Delay before retry `attempt`: `base_backoff * 2^attempt` scaled by a random
+/// factor in `[0.5, 1.5)`, so clients restarting together spread their
+/// reconnects, and capped at the longest un-jittered wait of `max_attempts`
+fn backoff_delay<R: Rng>(
+    rng: &mut R,
+    base_backoff: Duration,
+    attempt: usize,
+    max_attempts: usize,
+) -> Duration {
+    let cap = doubled(base_backoff, max_attempts.saturating_sub(2));
+    doubled(base_backoff, attempt)
+        .mul_f64(rng.gen_range(0.5, 1.5))
+        .min(cap)
+}
+
+/// `base * 2^times`, saturating at `MAX_CONNECT_BACKOFF` rather than
+/// overflowing for large retry counts or base delays
+fn doubled(base: Duration, times: usize) -> Duration {
+    u32::try_from(times)
+        .ok()
+        .and_then(|times| 1u32.checked_shl(times))
+        .and_then(|factor| base.checked_mul(factor))
+        .map_or(MAX_CONNECT_BACKOFF, |delay| delay.min(MAX_CONNECT_BACKOFF))
+}
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 struct EncResp {
@@ -107,7 +220,13 @@ struct EncResp {
 }
 
 impl EncResp {
-    fn new(public_key: String, encrypted_public_key: String, encrypted_private_key: String, user_token:String) -> Self {
+    #[allow(dead_code)]
+    fn new(
+        public_key: String,
+        encrypted_public_key: String,
+        encrypted_private_key: String,
+        user_token: String,
+    ) -> Self {
         EncResp {
             public_key,
             encrypted_public_key,
@@ -124,14 +243,67 @@ fn ppk_from_public_key(pubkey: &PublicKey) -> PPK {
     ppk
 }
 
+fn public_key_from_ppk(ppk: Option<&PPK>, name: &str) -> Result<PublicKey, String> {
+    let ppk = ppk.ok_or_else(|| format!("transform object has no {}", name))?;
+    PublicKey::new_from_slice((&ppk.x, &ppk.y))
+        .map_err(|err| format!("invalid {}: {:?}", name, err))
+}
+
+/// Inverse of the conversion in `fetch_content`: rebuild the recrypt
+/// value from a serialized transform object so it can be decrypted
+fn transformed_value_from_tfo(tfo: &TFO) -> Result<EncryptedValue, String> {
+    let invalid = |name: &str, err: RecryptErr| format!("invalid {}: {:?}", name, err);
+    let transform_blocks = tfo
+        .transform_blocks
+        .iter()
+        .map(transform_block_from_tfb)
+        .collect::<Result<Vec<_>, _>>()?;
+    // One block per hop, in the order the transforms were applied
+    let (first, rest) = transform_blocks
+        .split_first()
+        .ok_or("transform object has no transform block")?;
+
+    Ok(EncryptedValue::TransformedValue {
+        ephemeral_public_key: public_key_from_ppk(
+            tfo.ephemeral_public_key.as_ref(),
+            "ephemeral public key",
+        )?,
+        encrypted_message: EncryptedMessage::new_from_slice(&tfo.encrypted_message)
+            .map_err(|err| invalid("encrypted message", err))?,
+        auth_hash: AuthHash::new_from_slice(&tfo.auth_hash)
+            .map_err(|err| invalid("auth hash", err))?,
+        transform_blocks: NonEmptyVec::new(first.clone(), rest.to_vec()),
+        public_signing_key: PublicSigningKey::new_from_slice(&tfo.public_signing_key)
+            .map_err(|err| invalid("public signing key", err))?,
+        signature: Ed25519Signature::new_from_slice(&tfo.ed25519_signature)
+            .map_err(|err| invalid("signature", err))?,
+    })
+}
+
+fn transform_block_from_tfb(tfb: &TFB) -> Result<TransformBlock, String> {
+    let invalid = |name: &str, err: RecryptErr| format!("invalid {}: {:?}", name, err);
+    TransformBlock::new(
+        &public_key_from_ppk(tfb.public_key.as_ref(), "transform block public key")?,
+        &EncryptedTempKey::new_from_slice(&tfb.encrypted_temp_key)
+            .map_err(|err| invalid("encrypted temp key", err))?,
+        &public_key_from_ppk(
+            tfb.random_transform_public_key.as_ref(),
+            "random transform public key",
+        )?,
+        &EncryptedTempKey::new_from_slice(&tfb.encrypted_random_transform_temp_key)
+            .map_err(|err| invalid("encrypted random transform temp key", err))?,
+    )
+    .map_err(|err| invalid("transform block", err))
+}
+
 fn tfb_from_params(transform_block: &TransformBlock) -> TFB {
     let transform_block_pk = ppk_from_public_key(transform_block.public_key());
     let random_transform_pk = ppk_from_public_key(transform_block.random_transform_public_key());
     let mut tbf = TFB::new();
 
-    tbf.public_key = Some(PPK::from(transform_block_pk).into()).into();
+    tbf.public_key = Some(transform_block_pk).into();
     tbf.encrypted_temp_key = Vec::from(transform_block.encrypted_temp_key().bytes().as_slice());
-    tbf.random_transform_public_key = Some(PPK::from(random_transform_pk.clone()).into()).into();
+    tbf.random_transform_public_key = Some(random_transform_pk).into();
     tbf.encrypted_random_transform_temp_key = Vec::from(
         transform_block
             .encrypted_random_transform_temp_key()
@@ -141,42 +313,87 @@ fn tfb_from_params(transform_block: &TransformBlock) -> TFB {
     tbf
 }
 
-fn trans_response_from_params(
-    ephemeral_pk: &PublicKey,
-    transform_block: &TransformBlock,
-    transblock: &TFB,
-    transformed_obj: &TFO,
-) -> TransformedObject {
-    let random_transform_pk = ppk_from_public_key(transform_block.random_transform_public_key());
+/// Crypto parameters detected from the linked recrypt version at
+/// startup, so size checks follow the library instead of constants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CryptoParams {
+    pub plaintext_len: usize,
+    pub private_key_len: usize,
+    // Length of each of the x and y coordinates
+    pub public_key_len: usize,
+}
 
-    TransformedObject {
-        // TODO: Needs to construct additional Struct to split and show values
-        ephemeral_public_key: TransformPublicKeyCollection {
-            public_key_x: hex::encode(Vec::from(ephemeral_pk.bytes_x_y().0.as_slice())),
-            public_key_y: hex::encode(Vec::from(ephemeral_pk.bytes_x_y().1.as_slice())),
-        },
-        encrypted_message: hex::encode(&transformed_obj.encrypted_message),
-        auth_hash: hex::encode(&transformed_obj.auth_hash),
-        transform_blocks: TransformedBlockResponse {
-            public_key: TransformPublicKeyCollection {
-                public_key_x: hex::encode(&transblock.public_key.x),
-                public_key_y: hex::encode(&transblock.public_key.y),
-            },
-            encrypted_temp_key: hex::encode(&transblock.encrypted_temp_key),
-            encrypted_random_transform_temp_key: hex::encode(
-                &transblock.encrypted_random_transform_temp_key,
-            ),
-            random_transform_public_key: TransformPublicKeyCollection {
-                public_key_x: hex::encode(random_transform_pk.x),
-                public_key_y: hex::encode(random_transform_pk.y),
-            },
-        },
-        public_signing_key: hex::encode(&transformed_obj.public_signing_key),
-        ed25519_signature: hex::encode(&transformed_obj.ed25519_signature),
+impl CryptoParams {
+    /// Sizes of a freshly generated plaintext and key pair
+    pub fn detect() -> Result<Self, String> {
+        let recrypt = Recrypt::new();
+        let private_key = recrypt.random_private_key();
+        let public_key = recrypt
+            .compute_public_key(&private_key)
+            .map_err(|err| format!("Failed to detect recrypt key sizes: {:?}", err))?;
+        Ok(CryptoParams {
+            plaintext_len: recrypt.gen_plaintext().bytes().len(),
+            private_key_len: private_key.bytes().len(),
+            public_key_len: public_key.bytes_x_y().0.len(),
+        })
+    }
+
+    /// Check that user-supplied plaintext bytes can be turned into a `Plaintext`
+    pub fn validate_plaintext(&self, bytes: &[u8]) -> Result<(), String> {
+        if bytes.len() != self.plaintext_len {
+            return Err(format!(
+                "plaintext must be {} bytes, got {}",
+                self.plaintext_len,
+                bytes.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Issues the timestamp and nonce embedded in transformed objects
+/// when `--include-freshness` is set. They are added after recrypt signs
+/// the object and no signature covers them, so they are advisory: they
+/// help spot accidental duplicates but anyone can rewrite them.
+pub struct Freshness {
+    enabled: bool,
+    last_timestamp: AtomicU64,
+}
+
+impl Freshness {
+    pub fn new(enabled: bool) -> Self {
+        Freshness {
+            enabled,
+            last_timestamp: AtomicU64::new(0),
+        }
+    }
+
+    /// Milliseconds since the Unix epoch and a random nonce, or `None`
+    /// when disabled. Timestamps never go backwards, even if the clock does.
+    pub fn issue(&self) -> Option<(u64, Vec<u8>)> {
+        if !self.enabled {
+            return None;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        let timestamp = self
+            .last_timestamp
+            .fetch_max(now, Ordering::SeqCst)
+            .max(now);
+
+        let mut nonce = vec![0u8; FRESHNESS_NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        Some((timestamp, nonce))
     }
 }
 
-fn hardcoded_plaintext() -> Plaintext {
+/// The fixed plaintext used by `/fetch-content`, checked against the
+/// plaintext length the linked recrypt version expects
+pub fn hardcoded_plaintext(params: &CryptoParams) -> Result<Plaintext, String> {
     // Harcoded Plaintext generated with recrypt.gen_plaintext()
     let msg = vec![
         49, 99, 205, 79, 20, 51, 152, 222, 138, 58, 111, 88, 32, 103, 216, 127, 141, 68, 119, 226,
@@ -201,7 +418,19 @@ fn hardcoded_plaintext() -> Plaintext {
         232, 26, 32, 36, 49,
     ];
 
-    Plaintext::new_from_slice(&msg).unwrap()
+    params
+        .validate_plaintext(&msg)
+        .map_err(|err| format!("Hardcoded plaintext no longer fits recrypt: {}", err))?;
+    Plaintext::new_from_slice(&msg).map_err(|err| format!("Invalid hardcoded plaintext: {:?}", err))
+}
+
+/// The payload's resource as a `Plaintext`; it must be exactly one
+/// recrypt plaintext long
+fn plaintext_from_resource(params: &CryptoParams, resource: &[u8]) -> Result<Plaintext, String> {
+    params
+        .validate_plaintext(resource)
+        .map_err(|err| format!("resource {}", err))?;
+    Plaintext::new_from_slice(resource).map_err(|err| format!("Invalid resource: {:?}", err))
 }
 
 #[get("/")]
@@ -209,75 +438,421 @@ fn get_root() -> Json<String> {
     Json(String::from("Hola!!!"))
 }
 
+/// Largest JSON body the HTTP API reads, set by `--max-body-bytes`
+pub struct MaxBodyBytes(pub u64);
+
+/// Rocket stops reading a JSON body at the configured limit, so an
+/// oversized body shows up as a parse failure. Tell the two apart by the
+/// declared length.
+fn body_error(req: &Request, error: &str) -> ApiError {
+    let limit = req
+        .guard::<State<MaxBodyBytes>>()
+        .succeeded()
+        .map(|limit| limit.0);
+    let length = req
+        .headers()
+        .get_one("Content-Length")
+        .and_then(|length| length.parse::<u64>().ok());
+    match (limit, length) {
+        (Some(limit), Some(length)) if length > limit => ApiError::payload_too_large(format!(
+            "request body is {} bytes, the limit is {}",
+            length, limit
+        )),
+        _ => ApiError::bad_request(error.to_string()),
+    }
+}
 
-#[post("/", format = "json", data = "<payload>")]
-fn upload_content(payload: Json<Payload>) -> Json<String> {
-    // TODO: figure this out
-    println!("payload --- {:?}", payload);
-    println!();
+/// Rocket answers bodies that fail to parse as JSON with 400 or 422
+/// before any route runs; both become a JSON `ApiError` with 400 (or 413
+/// for oversized bodies) so clients see the same error shape as for
+/// validation failures
+#[catch(400)]
+fn bad_request(req: &Request) -> ApiError {
+    body_error(req, "malformed request body")
+}
 
-    Json(String::from("upload_content - work in progress"))
+#[catch(422)]
+fn unprocessable_entity(req: &Request) -> ApiError {
+    body_error(req, "request body does not match the expected JSON")
 }
 
-#[post("/", format = "json", data = "<payload>")]
-fn fetch_content(payload: Json<Payload>) -> Json<TransformedObjectResponse> {
-    println!("payload --- {:?}", payload);
-    println!();
+/// Request guard for routes whose body must be JSON. Unlike a route's
+/// `format = "json"`, which answers other types with 404, it fails with
+/// 415 before the body is read, and the `unsupported_media_type` catcher
+/// names the type that was sent.
+pub struct JsonContentType;
 
-    // Content Creator's Private Key
-    let initial_private_key = PrivateKey::new_from_slice(&payload.initial_private_key).unwrap();
+impl<'a, 'r> FromRequest<'a, 'r> for JsonContentType {
+    type Error = ();
 
-    // Content Creator's Public Key
-    let owner_public_key =
-        PublicKey::new_from_slice((&payload.initial_public_key_x, &payload.initial_public_key_y))
-            .unwrap();
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match request.content_type() {
+            Some(content_type) if content_type.is_json() => Outcome::Success(JsonContentType),
+            _ => Outcome::Failure((Status::UnsupportedMediaType, ())),
+        }
+    }
+}
 
-    // Bob's PK
-    let delegatee_public_key = PublicKey::new_from_slice((
-        &payload.delegatee_public_key_x,
-        &payload.delegatee_public_key_y,
+#[catch(415)]
+fn unsupported_media_type(req: &Request) -> ApiError {
+    let sent = req.content_type().map_or_else(
+        || "no Content-Type".to_string(),
+        |content_type| content_type.to_string(),
+    );
+    ApiError::unsupported_media_type(format!(
+        "Content-Type must be application/json, got {}",
+        sent
     ))
-        .unwrap();
+}
 
-    // *********************************************************************
-    let recrypt = Recrypt::new();
-    let signing_keypair = recrypt.generate_ed25519_key_pair();
-    // let plain_text = recrypt.gen_plaintext();
+/// When the HTTP API started, for `/health`'s uptime
+pub struct StartedAt(pub Instant);
 
-    let plain_text = hardcoded_plaintext();
-    let mut display = TransformedObject::default();
+/// Readiness probe for load balancers
+#[get("/health")]
+fn get_health(started_at: State<StartedAt>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_secs: started_at.0.elapsed().as_secs(),
+    })
+}
 
+/// Stores an `EncryptedResponse`, as returned by
+/// `/fetch-content?verbose=true`, under a new UUID
+#[post("/", data = "<payload>")]
+fn upload_content(
+    _api_key: ApiKey,
+    _json: JsonContentType,
+    payload: Json<EncryptedResponse>,
+    store: State<Box<dyn ResourceStore>>,
+) -> Result<Json<UploadResponse>, ApiError> {
+    debug!("Upload payload: {:?}", payload);
+    payload.validate().map_err(ApiError::bad_request)?;
+
+    let json = serde_json::to_vec(&*payload)
+        .map_err(|err| ApiError::internal(format!("Failed to serialize upload: {:?}", err)))?;
+    let id = new_resource_id();
+    store.insert(&id, &json)?;
+
+    Ok(Json(UploadResponse { id }))
+}
+
+/// Random (version 4) UUID, built here as no UUID crate is vendored
+fn new_resource_id() -> String {
+    let mut bytes = [0u8; RESOURCE_ID_LEN];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Returns an `EncryptedResponse` stored with `/upload-content`
+#[get("/<id>")]
+fn get_content(
+    _api_key: ApiKey,
+    id: String,
+    store: State<Box<dyn ResourceStore>>,
+) -> Result<Json<EncryptedResponse>, ApiError> {
+    let stored = store
+        .get(&id)?
+        .ok_or_else(|| ApiError::not_found(format!("no resource with id {}", id)))?;
+    let encrypted = serde_json::from_slice(&stored).map_err(|err| {
+        ApiError::internal(format!("Stored resource {} is corrupt: {:?}", id, err))
+    })?;
+
+    Ok(Json(encrypted))
+}
+
+/// Body of `/fetch-content`: JSON unless the client accepts
+/// `application/x-bincode`, and always JSON with `?verbose=true`
+#[derive(Responder)]
+enum FetchResponse {
+    Json(Json<TransformedObjectResponse>),
+    Verbose(Json<EncryptedResponse>),
+    Bincode(Content<Vec<u8>>),
+}
+
+fn bincode_media_type() -> MediaType {
+    MediaType::new("application", "x-bincode")
+}
+
+fn accepts_bincode(accept: Option<&Accept>) -> bool {
+    let bincode = bincode_media_type();
+    accept.map_or(false, |accept| {
+        accept.iter().any(|media| media.media_type() == &bincode)
+    })
+}
+
+/// Longest a request waits for its recrypt operations, set by
+/// `--crypto-timeout`; `None` waits indefinitely
+pub struct CryptoTimeout(pub Option<Duration>);
+
+/// Run `op` on its own thread and give up waiting after `timeout`,
+/// answering 503. This is a safety valve that frees the Rocket worker:
+/// the thread cannot be cancelled and finishes in the background.
+fn run_with_timeout<T, F>(timeout: Option<Duration>, op: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Ok(op()),
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        // The receiver is gone if the request already timed out
+        let _ = sender.send(op());
+    });
+
+    receiver.recv_timeout(timeout).map_err(|err| match err {
+        RecvTimeoutError::Timeout => ApiError::service_unavailable(format!(
+            "crypto operation did not finish within {:?}",
+            timeout
+        )),
+        RecvTimeoutError::Disconnected => {
+            ApiError::internal("crypto operation panicked".to_string())
+        }
+    })
+}
+
+/// Whether `/fetch-content` may honour `?include_transform_key=true`,
+/// set by `--allow-transform-key-disclosure`. The transform key lets its
+/// holder re-encrypt to the delegatee, so this is off by default.
+pub struct TransformKeyDisclosure(pub bool);
+
+/// Key callers must send in `X-Api-Key`, set by `--api-key`; `None`
+/// leaves every route open
+pub struct RequiredApiKey(pub Option<String>);
+
+/// Request guard for the routes that generate keys or handle content.
+/// Routes list it first, so a missing or wrong key fails the request
+/// with 401 before the body is read; the `unauthorized` catcher turns
+/// that into an `ApiError` body rather than Rocket's 401 page.
+pub struct ApiKey;
+
+impl<'a, 'r> FromRequest<'a, 'r> for ApiKey {
+    type Error = ApiError;
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        match api_key_error(request) {
+            None => Outcome::Success(ApiKey),
+            Some(err) => Outcome::Failure((Status::Unauthorized, err)),
+        }
+    }
+}
+
+/// Why `request` fails the `--api-key` check, or `None` if it passes
+fn api_key_error(request: &Request) -> Option<ApiError> {
+    let required = request.guard::<State<RequiredApiKey>>().succeeded();
+    let expected = required.as_ref().and_then(|required| required.0.as_ref())?;
+
+    match request.headers().get_one("X-Api-Key") {
+        Some(key) if ct_eq(key.as_bytes(), expected.as_bytes()) => None,
+        Some(_) => Some(ApiError::unauthorized("invalid API key".to_string())),
+        None => Some(ApiError::unauthorized(
+            "missing X-Api-Key header".to_string(),
+        )),
+    }
+}
+
+#[catch(401)]
+fn unauthorized(req: &Request) -> ApiError {
+    api_key_error(req).unwrap_or_else(|| ApiError::unauthorized("unauthorized".to_string()))
+}
+
+/// `transform_key` in the encoding of `Payload::transform_key`
+fn transform_key_bytes(transform_key: &TransformKey) -> Vec<u8> {
+    TransformKeyCollection::from(transform_key).to_bytes()
+}
+
+/// Inverse of `transform_key_bytes`. The key must target `delegatee`.
+fn parse_transform_key(bytes: &[u8], delegatee: &PublicKey) -> Result<TransformKey, String> {
+    let transform_key = TransformKey::try_from(&TransformKeyCollection::from_bytes(bytes)?)?;
+    if transform_key.to_public_key().bytes_x_y() != delegatee.bytes_x_y() {
+        return Err("transform_key does not target the delegatee public key".to_string());
+    }
+    Ok(transform_key)
+}
+
+/// Where `fetch_content` gets its transform key from
+#[derive(Clone)]
+enum TransformKeySource {
+    Supplied(TransformKey),
+    Generate(PrivateKey),
+}
+
+/// The recrypt values a `Payload` describes, validated
+#[derive(Clone)]
+struct TransformInputs {
+    plain_text: Plaintext,
+    owner_public_key: PublicKey,
+    delegatee_public_key: PublicKey,
+    transform_key_source: TransformKeySource,
+}
+
+impl TransformInputs {
+    fn from_payload(payload: &Payload, params: &CryptoParams) -> Result<Self, ApiError> {
+        // An empty resource falls back to the hardcoded demo plaintext
+        payload.validate(params).map_err(ApiError::bad_request)?;
+
+        // Content Creator's Public Key
+        let owner_public_key = PublicKey::new_from_slice((
+            &payload.initial_public_key_x,
+            &payload.initial_public_key_y,
+        ))
+        .map_err(|err| ApiError::bad_request(format!("invalid initial public key: {:?}", err)))?;
+
+        // Bob's PK
+        let delegatee_public_key = PublicKey::new_from_slice((
+            &payload.delegatee_public_key_x,
+            &payload.delegatee_public_key_y,
+        ))
+        .map_err(|err| ApiError::bad_request(format!("invalid delegatee public key: {:?}", err)))?;
+
+        // A supplied transform key makes the Content Creator's Private Key unnecessary
+        let transform_key_source = match &payload.transform_key {
+            Some(bytes) => TransformKeySource::Supplied(
+                parse_transform_key(bytes, &delegatee_public_key).map_err(ApiError::bad_request)?,
+            ),
+            None => TransformKeySource::Generate(
+                PrivateKey::new_from_slice(&payload.initial_private_key).map_err(|err| {
+                    ApiError::bad_request(format!("invalid initial_private_key: {:?}", err))
+                })?,
+            ),
+        };
+
+        let plain_text = if payload.resource.is_empty() {
+            hardcoded_plaintext(params).map_err(ApiError::internal)?
+        } else {
+            plaintext_from_resource(params, &payload.resource).map_err(ApiError::bad_request)?
+        };
+
+        Ok(TransformInputs {
+            plain_text,
+            owner_public_key,
+            delegatee_public_key,
+            transform_key_source,
+        })
+    }
+}
+
+/// recrypt's default instance. Its RNG sits behind a `Mutex` inside
+/// recrypt, so one instance is `Sync` and shared by every Rocket worker;
+/// concurrent requests only contend while drawing random bytes.
+pub type DefaultRecrypt = Recrypt<Sha256, Ed25519, RandomBytes<DefaultRng>>;
+
+/// Encrypt the plaintext to the owner and transform it to the delegatee,
+/// also returning the transform key bytes when `disclose_transform_key`
+fn encrypt_and_transform(
+    recrypt: &DefaultRecrypt,
+    inputs: TransformInputs,
+    signing_keypair: &SigningKeypair,
+    disclose_transform_key: bool,
+) -> Result<(EncryptedValue, Option<Vec<u8>>), ApiError> {
     let encrypted_val = recrypt
         .encrypt(
-            &plain_text,
-            &owner_public_key, // initial public key
-            &signing_keypair,  // signer key pair
+            &inputs.plain_text,
+            &inputs.owner_public_key, // initial public key
+            signing_keypair,          // signer key pair
         )
-        .unwrap();
+        .map_err(|err| ApiError::internal(format!("Encryption failed: {:?}", err)))?;
 
     // for this we need Bos,s public
-    let initial_to_target_transform_key = recrypt
-        .generate_transform_key(
-            &initial_private_key,  // initial private key
-            &delegatee_public_key, // target public key
-            &signing_keypair,
-        )
-        .unwrap();
+    let initial_to_target_transform_key = match inputs.transform_key_source {
+        TransformKeySource::Supplied(transform_key) => transform_key,
+        TransformKeySource::Generate(initial_private_key) => recrypt
+            .generate_transform_key(
+                &initial_private_key,         // initial private key
+                &inputs.delegatee_public_key, // target public key
+                signing_keypair,
+            )
+            .map_err(|err| {
+                ApiError::internal(format!("Failed to generate transform key: {:?}", err))
+            })?,
+    };
+
+    let disclosed_transform_key = if disclose_transform_key {
+        Some(transform_key_bytes(&initial_to_target_transform_key))
+    } else {
+        None
+    };
 
     // Transform the plaintext to be encrypted to the target!
     // The data is _not_ decrypted here. Simply transformed!
-    let transformed_val = recrypt
-        .transform(
-            encrypted_val,
-            initial_to_target_transform_key,
-            &signing_keypair,
-        )
-        .unwrap();
+    let transformed_val = verified_transform(
+        recrypt,
+        encrypted_val,
+        initial_to_target_transform_key,
+        signing_keypair,
+    )?;
+
+    Ok((transformed_val, disclosed_transform_key))
+}
 
-    let mut to = TFO::new();
+/// `recrypt.transform`, after checking the signature on `encrypted_val`
+/// with `verify_signature`, so a tampered value is rejected before any
+/// re-encryption work starts
+fn verified_transform(
+    recrypt: &DefaultRecrypt,
+    encrypted_val: EncryptedValue,
+    transform_key: TransformKey,
+    signing_keypair: &SigningKeypair,
+) -> Result<EncryptedValue, ApiError> {
+    if !verify_signature(recrypt, encrypted_val.clone())? {
+        return Err(ApiError::bad_request(
+            "Signature verification failed: invalid encrypted value signature".to_string(),
+        ));
+    }
+    recrypt
+        .transform(encrypted_val, transform_key, signing_keypair)
+        .map_err(transform_error)
+}
+
+/// Encrypt the payload's resource to the owner and transform it to the
+/// delegatee, as `/fetch-content` does. The delegatee's private key
+/// decrypts the result to the resource, or to `hardcoded_plaintext`
+/// when the resource is empty.
+pub fn transform_payload(
+    recrypt: &DefaultRecrypt,
+    payload: &Payload,
+    params: &CryptoParams,
+    signing_keypair: &SigningKeypair,
+) -> Result<EncryptedValue, String> {
+    let inputs = TransformInputs::from_payload(payload, params).map_err(|err| err.error)?;
+    let (transformed_val, _) =
+        encrypt_and_transform(recrypt, inputs, signing_keypair, false).map_err(|err| err.error)?;
+    Ok(transformed_val)
+}
+
+/// `recrypt.transform` verifies the ed25519 signatures on the encrypted
+/// value and on the transform key before re-encrypting. A failed check
+/// means tampered input, typically a supplied `transform_key`, so it is
+/// the caller's error rather than ours.
+fn transform_error(err: RecryptErr) -> ApiError {
+    match err {
+        RecryptErr::InvalidEncryptedMessageSignature(_) | RecryptErr::InvalidTransformKey(_) => {
+            ApiError::bad_request(format!("Signature verification failed: {}", err))
+        }
+        err => ApiError::internal(format!("Transform failed: {:?}", err)),
+    }
+}
 
-    println!("transformed_val {:?}", transformed_val);
-    println!();
+/// The protobuf transform object for a transformed value, along with
+/// its hex rendering for logs
+fn tfo_from_transformed(transformed_val: EncryptedValue) -> (TFO, TransformedObject) {
+    let mut to = TFO::new();
+    let mut display = TransformedObject::default();
 
     if let EncryptedValue::TransformedValue {
         ephemeral_public_key: ep,
@@ -289,106 +864,1198 @@ fn fetch_content(payload: Json<Payload>) -> Json<TransformedObjectResponse> {
     } = transformed_val
     {
         let ppk = ppk_from_public_key(&ep);
-        let transblock = tfb_from_params(tb.first());
+        let transblocks = tb.to_vec().iter().map(tfb_from_params).collect();
 
-        // End assigning
-
-        to.ephemeral_public_key = Some(PPK::from(ppk).into()).into();
+        to.ephemeral_public_key = Some(ppk).into();
         to.encrypted_message = Vec::from(em.bytes().as_slice());
         to.auth_hash = Vec::from(ah.bytes().as_slice());
-        to.transform_blocks = Some(TFB::from(transblock.clone()).into()).into();
+        to.transform_blocks = transblocks;
         to.public_signing_key = Vec::from(ps.bytes().as_slice());
         to.ed25519_signature = Vec::from(sg.bytes().as_slice());
 
-        println!("*************************************************************");
-        println!("0TGFBLOKC:- {:?}", to.transform_blocks);
-        println!("*************************************************************");
+        display = TransformedObject::from(&to);
+    };
 
-        // TODO: We might need this structure to deserialize and reconstruct the transform object
-        display = trans_response_from_params(&ep, tb.first(), &transblock, &to);
+    (to, display)
+}
 
-        println!("TransformedObject as Hex values \n {:?}", display);
+#[post("/?<include_transform_key>&<verbose>&<encoding>", data = "<payload>")]
+fn fetch_content(
+    _api_key: ApiKey,
+    include_transform_key: Option<bool>,
+    verbose: Option<bool>,
+    encoding: Option<Result<OutputEncoding, String>>,
+    accept: Option<&Accept>,
+    _json: JsonContentType,
+    payload: Json<Payload>,
+    recrypt: State<Arc<DefaultRecrypt>>,
+    freshness: State<Freshness>,
+    signing_keypair: State<SigningKeypair>,
+    params: State<CryptoParams>,
+    audit_log: State<AuditLog>,
+    disclosure: State<TransformKeyDisclosure>,
+    crypto_timeout: State<CryptoTimeout>,
+) -> Result<FetchResponse, ApiError> {
+    let encoding = match encoding {
+        Some(Ok(encoding)) => encoding,
+        Some(Err(err)) => return Err(ApiError::bad_request(err)),
+        None => OutputEncoding::default(),
     };
 
-    println!("Transform Object");
+    // Ignored unless the server opted in
+    let disclose_transform_key = disclosure.0 && include_transform_key.unwrap_or(false);
+
+    debug!("Fetch payload: {:?}", payload);
+
+    let inputs = TransformInputs::from_payload(&payload, &params)?;
+
+    // *********************************************************************
+    // Only the signing keypair and the recrypt instance are shared between
+    // requests. Each encrypt draws a fresh ephemeral key from recrypt's RNG,
+    // so the same plaintext never produces the same ciphertext; do not
+    // cache `encrypted_val`.
+    let signing_keypair = signing_keypair.inner().clone();
+    let signing_public_key = signing_keypair.public_key();
+    let recrypt = recrypt.inner().clone();
+
+    let transformed = run_with_timeout(crypto_timeout.0, move || {
+        encrypt_and_transform(&recrypt, inputs, &signing_keypair, disclose_transform_key)
+    })
+    .and_then(|result| result);
+
+    let owner_public_key_bytes = [
+        &payload.initial_public_key_x[..],
+        &payload.initial_public_key_y[..],
+    ]
+    .concat();
+    let delegatee_public_key_bytes = [
+        &payload.delegatee_public_key_x[..],
+        &payload.delegatee_public_key_y[..],
+    ]
+    .concat();
+    audit_log.record(
+        "transform",
+        &[
+            &owner_public_key_bytes[..],
+            &delegatee_public_key_bytes[..],
+            &signing_public_key.bytes()[..],
+        ],
+        &transformed,
+    );
+
+    let (transformed_val, disclosed_transform_key) = transformed?;
+
+    trace!("Transformed value: {:?}", transformed_val);
+
+    let (mut to, _) = tfo_from_transformed(transformed_val);
+
+    // Set after recrypt signed the value, so not covered by the signature
+    let freshness = freshness.issue();
+    if let Some((timestamp, nonce)) = &freshness {
+        to.timestamp = *timestamp;
+        to.nonce = nonce.clone();
+    }
+    let display = TransformedObject::from(&to);
+
+    trace!("Transform blocks: {:?}", to.transform_blocks);
+    debug!("TransformedObject:\n{}", display);
 
     // *********************************************************************************************
 
-    let tfo_bytes = to.write_to_bytes().unwrap();
+    let tfo_bytes = to.write_to_bytes().map_err(|err| {
+        ApiError::internal(format!("failed to serialize transform object: {}", err))
+    })?;
+
+    if verbose.unwrap_or(false) {
+        let response = EncryptedResponse {
+            sender_public_key: HexBytes::from(payload.initial_public_key_x.as_slice()),
+            encrypted_resource: HexBytes::from(payload.resource.as_slice()),
+            transformed: HexBytes::from(tfo_bytes.as_slice()),
+            // Passing this for test purposes
+            transformed_response: display,
+            transformed_object: Some(encoding)
+                .filter(|encoding| *encoding != OutputEncoding::Hex)
+                .map(|encoding| encoding.encode(&tfo_bytes)),
+            transform_key: disclosed_transform_key.map(HexBytes::from),
+        };
+        return Ok(FetchResponse::Verbose(Json(response)));
+    }
 
-    let response = EncryptedResponse {
-        sender_public_key: hex::encode(&payload.initial_public_key_x),
-        encrypted_resource: hex::encode(&payload.resource),
-        transformed: hex::encode(&tfo_bytes),
-        // Passing this for test purposes
-        transformed_response: display,
+    let (timestamp, nonce) = match freshness {
+        Some((timestamp, nonce)) => (Some(timestamp), Some(HexBytes::from(nonce))),
+        None => (None, None),
     };
 
+    if accepts_bincode(accept) {
+        let binary = BinaryTransformedObjectResponse {
+            transformed_object: tfo_bytes,
+            timestamp,
+            nonce: nonce.map(|nonce| nonce.0),
+            transform_key: disclosed_transform_key,
+        };
+        let body = bincode::serialize(&binary)
+            .map_err(|err| ApiError::internal(format!("Failed to encode response: {:?}", err)))?;
+        return Ok(FetchResponse::Bincode(Content(
+            ContentType(bincode_media_type()),
+            body,
+        )));
+    }
+
     let tr = TransformedObjectResponse {
-        transformed_object: hex::encode(&tfo_bytes),
+        transformed_object: encoding.encode(&tfo_bytes),
+        timestamp,
+        nonce,
+        transform_key: disclosed_transform_key.map(HexBytes::from),
+    };
+
+    Ok(FetchResponse::Json(Json(tr)))
+}
+/// Generates a transform key once, so it can be passed to any number of
+/// `/fetch-content` requests instead of the owner's private key
+#[post("/", format = "json", data = "<payload>")]
+fn create_transform_key(
+    _api_key: ApiKey,
+    payload: Json<TransformKeyPayload>,
+    recrypt: State<Arc<DefaultRecrypt>>,
+    signing_keypair: State<SigningKeypair>,
+    audit_log: State<AuditLog>,
+) -> Result<Json<TransformKeyResponse>, ApiError> {
+    let initial_private_key = PrivateKey::new_from_slice(&payload.initial_private_key)
+        .map_err(|err| ApiError::bad_request(format!("invalid initial_private_key: {:?}", err)))?;
+    let delegatee_public_key = PublicKey::new_from_slice((
+        &payload.delegatee_public_key_x,
+        &payload.delegatee_public_key_y,
+    ))
+    .map_err(|err| ApiError::bad_request(format!("invalid delegatee public key: {:?}", err)))?;
+
+    let transform_key = recrypt.generate_transform_key(
+        &initial_private_key,
+        &delegatee_public_key,
+        &signing_keypair,
+    );
+
+    let delegatee_public_key_bytes = [
+        &payload.delegatee_public_key_x[..],
+        &payload.delegatee_public_key_y[..],
+    ]
+    .concat();
+    audit_log.record(
+        "transform-key",
+        &[
+            &delegatee_public_key_bytes[..],
+            &signing_keypair.public_key().bytes()[..],
+        ],
+        &transform_key,
+    );
+    let transform_key = transform_key
+        .map_err(|err| ApiError::internal(format!("Transform key generation failed: {:?}", err)))?;
+
+    Ok(Json(TransformKeyResponse {
+        transform_key: HexBytes::from(transform_key_bytes(&transform_key)),
+    }))
+}
+
+/// Lets the delegatee recover the plaintext from a `/fetch-content`
+/// transformed object, to check the re-encryption end to end
+#[post("/", format = "json", data = "<payload>")]
+fn decrypt_content(
+    _api_key: ApiKey,
+    payload: Json<DecryptPayload>,
+    recrypt: State<Arc<DefaultRecrypt>>,
+    audit_log: State<AuditLog>,
+    crypto_timeout: State<CryptoTimeout>,
+) -> Result<Json<DecryptResponse>, ApiError> {
+    let (delegatee_private_key, delegatee_public_key) =
+        delegatee_key_pair(&recrypt, &payload.delegatee_private_key)?;
+
+    let tfo = tfo_from_hex(&payload.transformed_object).map_err(ApiError::bad_request)?;
+    let transformed_value = transformed_value_from_tfo(&tfo).map_err(ApiError::bad_request)?;
+
+    // Only public keys reach the audit log: the delegatee's is derived
+    // from the private key it sent
+    let (x, y) = delegatee_public_key.bytes_x_y();
+    let delegatee_public_key_bytes = [&x[..], &y[..]].concat();
+
+    let recrypt = recrypt.inner().clone();
+    let plaintext = run_with_timeout(crypto_timeout.0, move || {
+        recrypt.decrypt(transformed_value, &delegatee_private_key)
+    })
+    .and_then(|result| {
+        result.map_err(|err| ApiError::bad_request(format!("Decryption failed: {:?}", err)))
+    });
+
+    audit_log.record(
+        "decrypt",
+        &[&delegatee_public_key_bytes[..], &tfo.public_signing_key[..]],
+        &plaintext,
+    );
+    let plaintext = plaintext?;
+
+    Ok(Json(DecryptResponse {
+        plaintext: HexBytes::from(&plaintext.bytes()[..]),
+    }))
+}
+
+/// The delegatee private key sent to a decrypt route, with its public key
+fn delegatee_key_pair(
+    recrypt: &DefaultRecrypt,
+    private_key: &[u8],
+) -> Result<(PrivateKey, PublicKey), ApiError> {
+    let invalid = |err: RecryptErr| {
+        ApiError::bad_request(format!("invalid delegatee_private_key: {:?}", err))
     };
+    let private_key = PrivateKey::new_from_slice(private_key).map_err(invalid)?;
+    let public_key = recrypt.compute_public_key(&private_key).map_err(invalid)?;
+    Ok((private_key, public_key))
+}
 
-    Json(TransformedObjectResponse {
-        transformed_object: hex::encode(&tfo_bytes),
+/// Decrypts a resource stored with `/upload-content`, completing the
+/// store-and-retrieve flow. The stored object is re-encrypted with the
+/// body's `transform_key` first, when one is given.
+#[post("/<id>/decrypt", data = "<payload>")]
+fn decrypt_resource(
+    _api_key: ApiKey,
+    id: String,
+    _json: JsonContentType,
+    payload: Json<ResourceDecryptPayload>,
+    store: State<Box<dyn ResourceStore>>,
+    recrypt: State<Arc<DefaultRecrypt>>,
+    signing_keypair: State<SigningKeypair>,
+    audit_log: State<AuditLog>,
+    crypto_timeout: State<CryptoTimeout>,
+) -> Result<Json<DecryptResponse>, ApiError> {
+    let stored = store
+        .get(&id)?
+        .ok_or_else(|| ApiError::not_found(format!("no resource with id {}", id)))?;
+    let encrypted: EncryptedResponse = serde_json::from_slice(&stored).map_err(|err| {
+        ApiError::internal(format!("Stored resource {} is corrupt: {:?}", id, err))
+    })?;
+    let tfo = TFO::parse_from_bytes(&encrypted.transformed).map_err(|err| {
+        ApiError::internal(format!("Stored resource {} is corrupt: {:?}", id, err))
+    })?;
+    let transformed_value = transformed_value_from_tfo(&tfo).map_err(ApiError::internal)?;
+
+    let (delegatee_private_key, delegatee_public_key) =
+        delegatee_key_pair(&recrypt, &payload.delegatee_private_key)?;
+    let transform_key = match &payload.transform_key {
+        Some(bytes) => {
+            Some(parse_transform_key(bytes, &delegatee_public_key).map_err(ApiError::bad_request)?)
+        }
+        None => None,
+    };
+    let (x, y) = delegatee_public_key.bytes_x_y();
+    let delegatee_public_key_bytes = [&x[..], &y[..]].concat();
+
+    let signing_keypair = signing_keypair.inner().clone();
+    let recrypt = recrypt.inner().clone();
+    let plaintext = run_with_timeout(crypto_timeout.0, move || {
+        let transformed_value = match transform_key {
+            Some(transform_key) => recrypt
+                .transform(transformed_value, transform_key, &signing_keypair)
+                .map_err(transform_error)?,
+            None => transformed_value,
+        };
+        recrypt
+            .decrypt(transformed_value, &delegatee_private_key)
+            .map_err(|err| ApiError::bad_request(format!("Decryption failed: {:?}", err)))
     })
+    .and_then(|result| result);
+
+    audit_log.record(
+        "decrypt-resource",
+        &[&delegatee_public_key_bytes[..], &tfo.public_signing_key[..]],
+        &plaintext,
+    );
+    let plaintext = plaintext?;
+
+    Ok(Json(DecryptResponse {
+        plaintext: HexBytes::from(&plaintext.bytes()[..]),
+    }))
 }
-/// Gets Keys
+
+/// Whether the signature on `value` verifies against its embedded
+/// public signing key. recrypt only verifies inside `transform` and
+/// `decrypt`, so this transforms the value once more with a throwaway
+/// key and reports whether the signature check inside it passed.
+fn verify_signature(recrypt: &DefaultRecrypt, value: EncryptedValue) -> Result<bool, ApiError> {
+    let signing_keypair = recrypt.generate_ed25519_key_pair();
+    let (from_private_key, _) = generate_recrypt_key_pair(recrypt, None)?;
+    let (_, to_public_key) = generate_recrypt_key_pair(recrypt, None)?;
+    let transform_key = recrypt
+        .generate_transform_key(&from_private_key, &to_public_key, &signing_keypair)
+        .map_err(|err| {
+            ApiError::internal(format!("Failed to generate transform key: {:?}", err))
+        })?;
+
+    match recrypt.transform(value, transform_key, &signing_keypair) {
+        Ok(_) => Ok(true),
+        Err(RecryptErr::InvalidEncryptedMessageSignature(_)) => Ok(false),
+        Err(err) => Err(ApiError::internal(format!(
+            "Verification failed: {:?}",
+            err
+        ))),
+    }
+}
+
+/// Checks a transformed object's signature against its embedded
+/// `public_signing_key`, so a delegatee can verify before decrypting
+#[post("/", format = "json", data = "<payload>")]
+fn verify_transformed_object(
+    _api_key: ApiKey,
+    payload: Json<VerifyPayload>,
+    recrypt: State<Arc<DefaultRecrypt>>,
+    audit_log: State<AuditLog>,
+    crypto_timeout: State<CryptoTimeout>,
+) -> Result<Json<VerifyResponse>, ApiError> {
+    let tfo = tfo_from_hex(&payload.transformed_object).map_err(ApiError::bad_request)?;
+    let transformed_value = transformed_value_from_tfo(&tfo).map_err(ApiError::bad_request)?;
+
+    let recrypt = recrypt.inner().clone();
+    let valid = run_with_timeout(crypto_timeout.0, move || {
+        verify_signature(&recrypt, transformed_value)
+    })
+    .and_then(|result| result);
+
+    audit_log.record("verify", &[&tfo.public_signing_key[..]], &valid);
+    let valid = valid?;
+
+    Ok(Json(VerifyResponse { valid }))
+}
+
+/// Public half of the key that signs transformed objects, so clients
+/// can cache it and verify objects offline
 #[get("/")]
-fn get_key_pair() -> Json<Keys> {
-    let recrypt = Recrypt::new();
-    let (private_key, public_key) = recrypt.generate_key_pair().unwrap();
+fn get_signing_key(signing_keypair: State<SigningKeypair>) -> Json<SigningKeyResponse> {
+    Json(SigningKeyResponse {
+        public_signing_key: HexBytes::from(&signing_keypair.public_key().bytes()[..]),
+    })
+}
+
+/// Reports the crypto parameters this instance runs with, to
+/// diagnose interop issues between client and server versions
+#[get("/crypto")]
+fn get_crypto_diag(
+    params: State<CryptoParams>,
+    signing_keypair: State<SigningKeypair>,
+) -> Json<CryptoDiagnostics> {
+    Json(CryptoDiagnostics {
+        plaintext_len: params.plaintext_len,
+        private_key_len: params.private_key_len,
+        public_key_len: params.public_key_len,
+        recrypt_version: RECRYPT_VERSION.to_string(),
+        public_signing_key: HexBytes::from(&signing_keypair.public_key().bytes()[..]),
+    })
+}
+
+/// Run `keygen` up to `MAX_KEYGEN_ATTEMPTS` times, logging each failure,
+/// and report a 500 if none of the attempts succeed
+fn generate_key_pair_with_retry<T, E, F>(mut keygen: F) -> Result<T, ApiError>
+where
+    E: std::fmt::Debug,
+    F: FnMut() -> Result<T, E>,
+{
+    let mut err_msg = String::new();
 
-    println!("Public Key {:?}", public_key);
-    println!();
+    for attempt in 1..=MAX_KEYGEN_ATTEMPTS {
+        match keygen() {
+            Ok(pair) => return Ok(pair),
+            Err(e) => {
+                err_msg = format!("{:?}", e);
+                warn!(
+                    "Key generation attempt {}/{} failed: {}",
+                    attempt, MAX_KEYGEN_ATTEMPTS, err_msg
+                );
+            }
+        }
+    }
+
+    Err(ApiError::internal(format!(
+        "Key generation failed after {} attempts: {}",
+        MAX_KEYGEN_ATTEMPTS, err_msg
+    )))
+}
+
+/// Generate a recrypt key pair with `recrypt`, or deterministically
+/// from a throwaway seeded instance when `seed` is given
+fn generate_recrypt_key_pair(
+    recrypt: &DefaultRecrypt,
+    seed: Option<&KeySeed>,
+) -> Result<(PrivateKey, PublicKey), ApiError> {
+    match seed {
+        Some(seed) => {
+            let recrypt = Recrypt::new_with_rand(ChaCha20Rng::from_seed(seed.0));
+            generate_key_pair_with_retry(|| recrypt.generate_key_pair())
+        }
+        None => generate_key_pair_with_retry(|| recrypt.generate_key_pair()),
+    }
+}
+
+/// Gets Keys, with the public key coordinates in `coord_endian` order.
+/// With `seed`, the same seed always gives the same keys.
+#[get("/?<coord_endian>&<seed>")]
+fn get_key_pair(
+    _api_key: ApiKey,
+    coord_endian: Option<Result<CoordEndian, &RawStr>>,
+    seed: Option<Result<KeySeed, String>>,
+    recrypt: State<Arc<DefaultRecrypt>>,
+    audit_log: State<AuditLog>,
+) -> Result<Json<Keys>, ApiError> {
+    let coord_endian = match coord_endian {
+        Some(Ok(coord_endian)) => coord_endian,
+        Some(Err(value)) => {
+            return Err(ApiError::bad_request(format!(
+                "coord_endian must be be or le, got {}",
+                value
+            )))
+        }
+        None => CoordEndian::default(),
+    };
+    let seed = match seed {
+        Some(Ok(seed)) => Some(seed),
+        Some(Err(err)) => return Err(ApiError::bad_request(err)),
+        None => None,
+    };
+    let key_pair = generate_recrypt_key_pair(&recrypt, seed.as_ref());
+    let public_key_bytes = match &key_pair {
+        Ok((_, public_key)) => {
+            [&public_key.bytes_x_y().0[..], &public_key.bytes_x_y().1[..]].concat()
+        }
+        Err(_) => Vec::new(),
+    };
+    audit_log.record("keygen", &[&public_key_bytes[..]], &key_pair);
+    let (private_key, public_key) = key_pair?;
+
+    debug!("Public key: {:?}", public_key);
 
     let pk = PPK::new();
-    let bbs = protobuf::Message::write_to_bytes(&pk).unwrap();
+    let bbs = protobuf::Message::write_to_bytes(&pk)
+        .map_err(|err| ApiError::internal(format!("failed to serialize public key: {}", err)))?;
 
-    println!("Public Key ---- {:?}", bbs);
-    println!();
+    trace!("Serialized public key: {:?}", bbs);
 
     let keys = Keys {
-        private_key: Vec::from(private_key.bytes().as_slice()),
-        public_key_x: Vec::from(public_key.bytes_x_y().0.as_slice()),
-        public_key_y: Vec::from(public_key.bytes_x_y().1.as_slice()),
+        private_key: SecretBytes::from(private_key.bytes().as_slice()),
+        public_key_x: coord_endian.apply(public_key.bytes_x_y().0.as_slice()),
+        public_key_y: coord_endian.apply(public_key.bytes_x_y().1.as_slice()),
     };
-    Json(keys)
+    Ok(Json(keys))
+}
+/// `Json` responses, including `ApiError` bodies, are sent as plain
+/// `application/json`; spell out the charset for strict clients.
+fn json_charset(_: &Request, response: &mut Response) {
+    if response.content_type() == Some(ContentType::JSON) {
+        response.set_header(ContentType(MediaType::with_params(
+            "application",
+            "json",
+            ("charset", "utf-8"),
+        )));
+    }
 }
-/// Starting point of the Enclave Parent Instance
-pub fn client(_args: ClientArgs) -> Result<(), String> {
+
+/// Every route is served with and without a trailing slash. The slash
+/// is stripped before routing so `/get-keys/` and `/get-keys` reach the
+/// same handler regardless of how the router treats empty segments.
+fn strip_trailing_slash(request: &mut Request, _: &Data) {
+    let normalized = {
+        let uri = request.uri();
+        let path = uri.path();
+        if path.len() <= 1 || !path.ends_with('/') {
+            return;
+        }
+
+        let mut normalized = path.trim_end_matches('/').to_string();
+        if normalized.is_empty() {
+            normalized.push('/');
+        }
+        if let Some(query) = uri.query() {
+            normalized.push('?');
+            normalized.push_str(query);
+        }
+        normalized
+    };
+
+    match Origin::parse_owned(normalized) {
+        Ok(origin) => request.set_uri(origin),
+        Err(e) => warn!("Failed to normalize request path: {:?}", e),
+    }
+}
+
+/// One line summary of the effective configuration, logged at startup
+#[derive(Serialize)]
+struct StartupBanner<'a, T: Serialize> {
+    version: &'static str,
+    mode: &'a str,
+    address: &'a str,
+    features: &'a [&'a str],
+    settings: &'a T,
+}
+
+/// The startup banner as JSON. `settings` is serialized as-is, so it
+/// must not hold secrets unless they serialize redacted.
+pub fn startup_banner<T: Serialize>(
+    mode: &str,
+    address: &str,
+    features: &[&str],
+    settings: &T,
+) -> Result<String, String> {
+    let banner = StartupBanner {
+        version: env!("CARGO_PKG_VERSION"),
+        mode,
+        address,
+        features,
+        settings,
+    };
+    serde_json::to_string(&banner)
+        .map_err(|err| format!("Failed to serialize startup banner: {:?}", err))
+}
+
+/// Starting point of the Enclave Parent Instance. Requests an ed25519
+/// key pair from the enclave server at `args.cid` and `args.port`, or
+/// serves the HTTP API with `--http`.
+pub fn client(args: ClientArgs) -> Result<(), String> {
+    proto_helpers::check_version()?;
+    if args.http {
+        return serve_http(args);
+    }
+
+    let vsocket = vsock_connect(
+        args.cid,
+        args.port,
+        args.connect_retries,
+        Duration::from_millis(args.connect_backoff_ms),
+    )?;
+    let keys = request_enclave_keys(vsocket.as_raw_fd())?;
+    let json = serde_json::to_string_pretty(&keys)
+        .map(Zeroizing::new)
+        .map_err(|err| format!("Failed to serialize keys: {:?}", err))?;
+    println!("{}", *json);
+
+    Ok(())
+}
+
+/// Client side of `handle_connection`: send a throwaway ECIES public key
+/// and decrypt the two halves of the key pair the server answers with
+fn request_enclave_keys(fd: RawFd) -> Result<Ed25519Keys, String> {
+    let mut csprng = OsRng {};
+    let (secret, public) = ecies_ed25519::generate_keypair(&mut csprng);
+    send_message(fd, public.as_bytes())?;
+
+    let mut reader = MessageReader::new(fd, MAX_KEY_MESSAGE_LEN);
+    let mut next_half = |half: &str| -> Result<Vec<u8>, String> {
+        let encrypted = reader.next_message()?.ok_or_else(|| {
+            format!(
+                "Server closed the connection before sending the {} key",
+                half
+            )
+        })?;
+        ecies_ed25519::decrypt(&secret, &encrypted)
+            .map_err(|err| format!("Failed to decrypt the {} key: {:?}", half, err))
+    };
+    let public_key = next_half("public")?;
+    let private_key = Zeroizing::new(next_half("private")?);
+
+    Ok(Ed25519Keys {
+        private_key: HexBytes::from(private_key.as_slice()),
+        public_key: HexBytes::from(public_key.as_slice()),
+    })
+}
+
+/// The HTTP API for `args`, ready to launch
+fn build_rocket(args: &ClientArgs) -> Result<rocket::Rocket, String> {
+    let audit_log = match &args.audit_log {
+        Some(path) => AuditLog::open(path)?,
+        None => AuditLog::disabled(),
+    };
+    let store = open_store(&args.store, args.max_resources, args.store_eviction)?;
+    let recrypt = Recrypt::new();
+
+    let allowed_origins = if args.cors_origin == DEFAULT_CORS_ORIGIN {
+        AllowedOrigins::all()
+    } else {
+        AllowedOrigins::some_exact(&[args.cors_origin.as_str()])
+    };
+    // The fairing also answers OPTIONS preflights for the POST routes
     let cors = CorsOptions::default()
-        .allowed_origins(AllowedOrigins::all())
+        .allowed_origins(allowed_origins)
         .allowed_methods(
             vec![Method::Get, Method::Post, Method::Patch]
                 .into_iter()
                 .map(From::from)
                 .collect(),
         )
-        .allow_credentials(true);
+        .allow_credentials(true)
+        .to_cors()
+        .map_err(|err| format!("Invalid CORS configuration: {}", err))?;
 
     let config = Config::build(Environment::Staging)
-        .address("0.0.0.0")
+        .address(args.address.to_string())
         .port(8000)
         .workers(4)
         .log_level(LoggingLevel::Debug)
         .keep_alive(5)
         .read_timeout(5)
         .write_timeout(5)
+        .limits(Limits::new().limit("json", args.max_body_bytes))
         .unwrap();
 
-    rocket::ignite().attach(cors.to_cors().unwrap())
-        .mount("/", routes![get_root])
+    let mut rocket = rocket::custom(config)
+        .attach(cors)
+        .attach(AdHoc::on_request(
+            "Strip trailing slash",
+            strip_trailing_slash,
+        ))
+        .attach(AdHoc::on_response("JSON charset", json_charset))
+        .manage(CryptoParams::detect()?)
+        .manage(Freshness::new(args.include_freshness))
+        .manage(recrypt.generate_ed25519_key_pair())
+        .manage(Arc::new(recrypt))
+        .manage(audit_log)
+        .manage(store)
+        .manage(TransformKeyDisclosure(args.allow_transform_key_disclosure))
+        .manage(RequiredApiKey(args.api_key.clone()))
+        .manage(StartedAt(Instant::now()))
+        .manage(MaxBodyBytes(args.max_body_bytes))
+        .manage(CryptoTimeout(args.crypto_timeout.map(Duration::from_secs)))
+        .register(catchers![
+            bad_request,
+            unauthorized,
+            unprocessable_entity,
+            unsupported_media_type
+        ])
+        .mount("/", routes![get_root, get_health])
         .mount("/get-keys", routes![get_key_pair]) // get
+        .mount("/signing-key", routes![get_signing_key]) // get
         .mount("/upload-content", routes![upload_content]) // post
-        .mount("/fetch-content", routes![fetch_content]) // post
-        .launch();
+        .mount("/content", routes![get_content]) // get
+        .mount("/resources", routes![decrypt_resource]) // post
+        .mount("/decrypt", routes![decrypt_content]) // post
+        .mount("/verify", routes![verify_transformed_object]) // post
+        .mount("/transform-key", routes![create_transform_key]) // post
+        .mount("/fetch-content", routes![fetch_content]); // post
+
+    if args.enable_debug_endpoints {
+        rocket = rocket.mount("/diag", routes![get_crypto_diag]); // get
+    }
+
+    Ok(rocket)
+}
+
+fn serve_http(args: ClientArgs) -> Result<(), String> {
+    let rocket = build_rocket(&args)?;
+    let address = format!("{}:{}", rocket.config().address, rocket.config().port);
+
+    let enabled = [
+        ("cors", true),
+        ("freshness", args.include_freshness),
+        ("debug-endpoints", args.enable_debug_endpoints),
+        ("audit-log", args.audit_log.is_some()),
+        (
+            "transform-key-disclosure",
+            args.allow_transform_key_disclosure,
+        ),
+        ("crypto-timeout", args.crypto_timeout.is_some()),
+        ("api-key", args.api_key.is_some()),
+    ];
+    let features: Vec<&str> = enabled
+        .iter()
+        .filter(|(_, on)| *on)
+        .map(|(name, _)| *name)
+        .collect();
+    // init_logging in main installed the logger, so this is not lost
+    // to the one rocket::custom would otherwise set up
+    info!("{}", startup_banner("client", &address, &features, &args)?);
+
+    rocket.launch();
+
+    Ok(())
+}
+
+/// Bind `socket_fd` to the first available port in `ports`,
+/// returning the port that was bound
+fn vsock_bind(socket_fd: RawFd, ports: PortRange) -> Result<u32, String> {
+    if ports == PortRange::single(0) {
+        return vsock_bind_ephemeral(socket_fd);
+    }
+
+    let mut err_msg = String::new();
+
+    for port in ports.ports() {
+        let sockaddr = SockAddr::new_vsock(VMADDR_CID_ANY, port);
+        match bind(socket_fd, &sockaddr) {
+            Ok(_) => return Ok(port),
+            Err(e) => err_msg = format!("Bind failed on port {}: {:?}", port, e),
+        }
+    }
+
+    Err(format!("No bindable port in range {}: {}", ports, err_msg))
+}
+
+/// Bind `socket_fd` to a free port picked by the kernel and return it
+fn vsock_bind_ephemeral(socket_fd: RawFd) -> Result<u32, String> {
+    bind(
+        socket_fd,
+        &SockAddr::new_vsock(VMADDR_CID_ANY, VMADDR_PORT_ANY),
+    )
+    .map_err(|err| format!("Bind failed on an ephemeral port: {:?}", err))?;
+    match getsockname(socket_fd)
+        .map_err(|err| format!("Failed to read the bound address: {:?}", err))?
+    {
+        SockAddr::Vsock(addr) => Ok(addr.port()),
+        addr => Err(format!("Bound to a non-vsock address: {}", addr)),
+    }
+}
+
+/// Disable Nagle-style coalescing on `fd` so small handshake messages go
+/// out immediately. Returns whether the option was applied: AF_VSOCK has
+/// no such buffering and rejects the option, which is not an error.
+fn set_no_delay(fd: RawFd) -> Result<bool, String> {
+    match setsockopt(fd, TcpNoDelay, &true) {
+        Ok(()) => Ok(true),
+        Err(nix::Error::Sys(Errno::ENOPROTOOPT))
+        | Err(nix::Error::Sys(Errno::EOPNOTSUPP))
+        | Err(nix::Error::Sys(Errno::EINVAL)) => Ok(false),
+        Err(err) => Err(format!("Failed to set no-delay: {:?}", err)),
+    }
+}
+
+/// Counting semaphore bounding how many vsock connections are
+/// handled at the same time
+struct ConnectionLimiter {
+    active: Mutex<usize>,
+    released: Condvar,
+    max: usize,
+}
+
+impl ConnectionLimiter {
+    fn new(max: usize) -> Self {
+        ConnectionLimiter {
+            active: Mutex::new(0),
+            released: Condvar::new(),
+            max,
+        }
+    }
+
+    /// Block until a connection slot is free and take it, or return
+    /// `None` once `shutdown` is set
+    fn acquire(self: &Arc<Self>, shutdown: &AtomicBool) -> Option<ConnectionPermit> {
+        let mut active = self.active.lock().unwrap();
+        loop {
+            // Checked on every wake-up, as the signal handler cannot
+            // notify the condition variable
+            if shutdown.load(Ordering::SeqCst) {
+                return None;
+            }
+            if *active < self.max {
+                break;
+            }
+            active = self
+                .released
+                .wait_timeout(active, SHUTDOWN_POLL_INTERVAL)
+                .unwrap()
+                .0;
+        }
+        *active += 1;
+        Some(ConnectionPermit(Arc::clone(self)))
+    }
+}
+
+/// Slot held for the lifetime of a connection, released on drop
+struct ConnectionPermit(Arc<ConnectionLimiter>);
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut active = self.0.active.lock().unwrap();
+        *active -= 1;
+        self.0.released.notify_one();
+    }
+}
+
+/// Generate an ed25519 key pair, returned as (private, public) key bytes.
+/// Callers should wrap the private half in `Zeroizing`.
+pub fn generate_ed25519_keypair() -> (Vec<u8>, Vec<u8>) {
+    let mut csprng = OsRng {};
+    let keypair: Keypair = Keypair::generate(&mut csprng);
+
+    (
+        Vec::from(&keypair.secret.as_bytes()[..]),
+        Vec::from(&keypair.public.as_bytes()[..]),
+    )
+}
+
+/// Generate an ed25519 key pair and write it to `args.output` as JSON
+pub fn ed25519_keygen(args: Ed25519KeygenArgs) -> Result<(), String> {
+    let (private_key, public_key) = generate_ed25519_keypair();
+    let keys = Ed25519Keys {
+        private_key: HexBytes::from(private_key),
+        public_key: HexBytes::from(public_key),
+    };
+
+    let json = serde_json::to_string_pretty(&keys)
+        .map(Zeroizing::new)
+        .map_err(|err| format!("Failed to serialize keys: {:?}", err))?;
+    std::fs::write(&args.output, json.as_bytes())
+        .map_err(|err| format!("Failed to write {}: {:?}", args.output, err))?;
+    println!("ED25519 key pair written to {}", args.output);
 
     Ok(())
 }
 
-/// Accept connections on a certain port and print
-/// the received data
+/// Generate a recrypt key pair without the HTTP server and write it as
+/// `Keys` JSON to `args.output`, or to stdout
+pub fn keygen(args: KeygenArgs) -> Result<(), String> {
+    let (private_key, public_key) =
+        generate_recrypt_key_pair(&Recrypt::new(), args.seed.as_ref()).map_err(|err| err.error)?;
+    let keys = Keys {
+        private_key: SecretBytes::from(private_key.bytes().as_slice()),
+        public_key_x: Vec::from(public_key.bytes_x_y().0.as_slice()),
+        public_key_y: Vec::from(public_key.bytes_x_y().1.as_slice()),
+    };
+
+    let json = serde_json::to_string_pretty(&keys)
+        .map(Zeroizing::new)
+        .map_err(|err| format!("Failed to serialize keys: {:?}", err))?;
+    match &args.output {
+        Some(output) => {
+            std::fs::write(output, json.as_bytes())
+                .map_err(|err| format!("Failed to write {}: {:?}", output, err))?;
+            println!("Keys written to {}", output);
+        }
+        None => println!("{}", *json),
+    }
+
+    Ok(())
+}
+
+/// Split `data` into recrypt plaintexts, zero-padding the last one.
+/// Each 32 byte coordinate of a plaintext holds a zero byte followed by
+/// 31 data bytes, which keeps it below the field modulus so the bytes
+/// survive the round trip unchanged. Callers record the original length
+/// to strip the padding after decrypting.
+pub fn chunk_into_plaintexts(data: &[u8]) -> Result<Vec<Plaintext>, String> {
+    if data.is_empty() {
+        return Err("no data to encrypt".to_string());
+    }
+    data.chunks(PLAINTEXT_CHUNK_LEN)
+        .map(|chunk| {
+            let mut bytes = Zeroizing::new(vec![0u8; PLAINTEXT_COORDS * PLAINTEXT_COORD_LEN]);
+            for (coord, data) in bytes
+                .chunks_mut(PLAINTEXT_COORD_LEN)
+                .zip(chunk.chunks(PLAINTEXT_COORD_LEN - 1))
+            {
+                coord[1..=data.len()].copy_from_slice(data);
+            }
+            Plaintext::new_from_slice(&bytes)
+                .map_err(|err| format!("Invalid plaintext chunk: {:?}", err))
+        })
+        .collect()
+}
+
+/// Inverse of `chunk_into_plaintexts`: the data bytes of `plaintexts`,
+/// in order, with the padding after `original_len` stripped
+pub fn unchunk_plaintexts(
+    plaintexts: &[Plaintext],
+    original_len: usize,
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    let mut data = Zeroizing::new(Vec::with_capacity(plaintexts.len() * PLAINTEXT_CHUNK_LEN));
+    for plaintext in plaintexts {
+        for coord in plaintext.bytes().chunks(PLAINTEXT_COORD_LEN) {
+            data.extend_from_slice(&coord[1..]);
+        }
+    }
+    if original_len > data.len() {
+        return Err(format!(
+            "original_len {} exceeds the {} decrypted bytes",
+            original_len,
+            data.len()
+        ));
+    }
+    data.truncate(original_len);
+    Ok(data)
+}
+
+/// Decrypt the output of `encrypt --plaintext-file` with the delegatee's
+/// private key, back to the original file contents
+pub fn decrypt_chunked(
+    recrypt: &DefaultRecrypt,
+    chunked: &ChunkedTransformResponse,
+    private_key: &PrivateKey,
+) -> Result<Zeroizing<Vec<u8>>, String> {
+    let plaintexts = chunked
+        .chunks
+        .iter()
+        .map(|chunk| {
+            let tfo = tfo_from_hex(&chunk.transformed_object)?;
+            let transformed_value = transformed_value_from_tfo(&tfo)?;
+            recrypt
+                .decrypt(transformed_value, private_key)
+                .map_err(|err| format!("Decryption failed: {:?}", err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    unchunk_plaintexts(&plaintexts, chunked.original_len)
+}
+
+/// Contents of the file `input`, or of stdin when absent
+fn read_input(input: &Option<String>) -> Result<Zeroizing<String>, String> {
+    match input {
+        Some(input) => std::fs::read_to_string(input)
+            .map_err(|err| format!("Failed to read {}: {:?}", input, err)),
+        None => {
+            let mut json = String::new();
+            std::io::stdin()
+                .read_to_string(&mut json)
+                .map(|_| json)
+                .map_err(|err| format!("Failed to read stdin: {:?}", err))
+        }
+    }
+    .map(Zeroizing::new)
+}
+
+/// Generate a transform key from a `TransformKeyPayload` JSON read from
+/// `args.input`, or stdin, and write it to `args.output` so
+/// `encrypt --transform-key-file` can reuse it. The key is signed with a
+/// throwaway signing key, which recrypt embeds in it.
+pub fn run_transform_key(args: TransformKeyArgs) -> Result<(), String> {
+    let json = read_input(&args.input)?;
+    let payload: TransformKeyPayload =
+        serde_json::from_str(&json).map_err(|err| format!("Failed to parse payload: {:?}", err))?;
+    let initial_private_key = PrivateKey::new_from_slice(&payload.initial_private_key)
+        .map_err(|err| format!("invalid initial_private_key: {:?}", err))?;
+    let delegatee_public_key = PublicKey::new_from_slice((
+        &payload.delegatee_public_key_x,
+        &payload.delegatee_public_key_y,
+    ))
+    .map_err(|err| format!("invalid delegatee public key: {:?}", err))?;
+
+    let recrypt = Recrypt::new();
+    let signing_keypair = recrypt.generate_ed25519_key_pair();
+    let transform_key = recrypt
+        .generate_transform_key(
+            &initial_private_key,
+            &delegatee_public_key,
+            &signing_keypair,
+        )
+        .map_err(|err| format!("Transform key generation failed: {:?}", err))?;
+
+    let json = serde_json::to_string_pretty(&TransformKeyCollection::from(&transform_key))
+        .map_err(|err| format!("Failed to serialize transform key: {:?}", err))?;
+    std::fs::write(&args.output, json.as_bytes())
+        .map_err(|err| format!("Failed to write {}: {:?}", args.output, err))?;
+    println!("Transform key written to {}", args.output);
+
+    Ok(())
+}
+
+/// Run the `/fetch-content` transform on a `Payload` JSON read from
+/// `args.input`, or stdin, and print the `TransformedObjectResponse`.
+/// With `args.plaintext_file`, the file's contents are encrypted in
+/// plaintext-sized chunks instead of the payload's resource and a
+/// `ChunkedTransformResponse` is printed. With `args.dump_proto`, the
+/// raw `TransformObject` protobuf is also written to that file. Objects
+/// are signed with a throwaway signing key.
+pub fn run_encrypt(args: EncryptArgs) -> Result<(), String> {
+    let json = read_input(&args.input)?;
+    let mut payload: Payload =
+        serde_json::from_str(&json).map_err(|err| format!("Failed to parse payload: {:?}", err))?;
+    if let Some(path) = &args.transform_key_file {
+        let transform_key = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {:?}", path, err))?;
+        let transform_key: TransformKeyCollection = serde_json::from_str(&transform_key)
+            .map_err(|err| format!("Failed to parse transform key {}: {:?}", path, err))?;
+        payload.transform_key = Some(HexBytes::from(transform_key.to_bytes()));
+    }
+
+    let inputs = TransformInputs::from_payload(&payload, &CryptoParams::detect()?)
+        .map_err(|err| err.error)?;
+    let recrypt = Recrypt::new();
+    let signing_keypair = recrypt.generate_ed25519_key_pair();
+    let transform = |inputs: TransformInputs| -> Result<TransformedObjectResponse, String> {
+        let (transformed_val, _) = encrypt_and_transform(&recrypt, inputs, &signing_keypair, false)
+            .map_err(|err| err.error)?;
+        let (to, _) = tfo_from_transformed(transformed_val);
+        // Only set for a single object; clap rejects it with `--plaintext-file`
+        if let Some(path) = &args.dump_proto {
+            write_proto_to_file(&to, Path::new(path))?;
+        }
+        let tfo_bytes = to
+            .write_to_bytes()
+            .map_err(|err| format!("failed to serialize transform object: {}", err))?;
+        Ok(TransformedObjectResponse {
+            transformed_object: OutputEncoding::Hex.encode(&tfo_bytes),
+            timestamp: None,
+            nonce: None,
+            transform_key: None,
+        })
+    };
+
+    let json = match &args.plaintext_file {
+        Some(path) => {
+            let data = std::fs::read(path)
+                .map(Zeroizing::new)
+                .map_err(|err| format!("Failed to read {}: {:?}", path, err))?;
+            let chunks = chunk_into_plaintexts(&data)?
+                .into_iter()
+                .map(|plain_text| {
+                    transform(TransformInputs {
+                        plain_text,
+                        ..inputs.clone()
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            serde_json::to_string_pretty(&ChunkedTransformResponse {
+                original_len: data.len(),
+                chunks,
+            })
+        }
+        None => serde_json::to_string_pretty(&transform(inputs)?),
+    }
+    .map_err(|err| format!("Failed to serialize response: {:?}", err))?;
+    println!("{}", json);
+
+    Ok(())
+}
+
+/// Why `accept_with_deadline` returned without a connection
+#[derive(Debug, PartialEq, Eq)]
+pub enum AcceptError {
+    DeadlineExpired,
+    // Worth retrying: interrupted, aborted by the peer, or out of descriptors
+    Recoverable(String),
+    Failed(String),
+}
+
+impl fmt::Display for AcceptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AcceptError::DeadlineExpired => write!(f, "No connection before the accept deadline"),
+            AcceptError::Recoverable(msg) | AcceptError::Failed(msg) => {
+                write!(f, "Accept failed: {}", msg)
+            }
+        }
+    }
+}
+
+/// Accept a connection on the listening socket `fd`, giving up with
+/// `AcceptError::DeadlineExpired` once `deadline` has passed
+pub fn accept_with_deadline(fd: RawFd, deadline: Instant) -> Result<RawFd, AcceptError> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            return Err(AcceptError::DeadlineExpired);
+        }
+
+        let timeout_ms = remaining.as_millis().min(i32::MAX as u128) as i32;
+        let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+        match poll(&mut fds, timeout_ms) {
+            Ok(0) => return Err(AcceptError::DeadlineExpired),
+            Ok(_) => return accept(fd).map_err(accept_error),
+            Err(nix::Error::Sys(Errno::EINTR)) => continue,
+            Err(err) => return Err(AcceptError::Failed(format!("{:?}", err))),
+        }
+    }
+}
+
+fn accept_error(err: nix::Error) -> AcceptError {
+    match err {
+        nix::Error::Sys(Errno::EINTR)
+        | nix::Error::Sys(Errno::ECONNABORTED)
+        | nix::Error::Sys(Errno::EMFILE)
+        | nix::Error::Sys(Errno::ENFILE) => AcceptError::Recoverable(format!("{:?}", err)),
+        err => AcceptError::Failed(format!("{:?}", err)),
+    }
+}
+
+extern "C" fn request_shutdown(_: c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Make SIGTERM stop the accept loop in `server` instead of killing
+/// the process
+fn install_shutdown_handler() -> Result<(), String> {
+    let action = SigAction::new(
+        SigHandler::Handler(request_shutdown),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    // Safe: the handler only stores to an atomic
+    unsafe { sigaction(Signal::SIGTERM, &action) }
+        .map(|_| ())
+        .map_err(|err| format!("Failed to install SIGTERM handler: {:?}", err))
+}
+
+/// Re-serialize the `Keys` JSON in `args.input`, written with either byte
+/// arrays or hex strings, to `args.output` in the `args.to` format
+pub fn convert_keys(args: ConvertKeysArgs) -> Result<(), String> {
+    let input = std::fs::read_to_string(&args.input)
+        .map(Zeroizing::new)
+        .map_err(|err| format!("Failed to read {}: {:?}", args.input, err))?;
+    let keys: Keys = serde_json::from_str(&input)
+        .map_err(|err| format!("Failed to parse keys in {}: {:?}", args.input, err))?;
+
+    let output = match args.to {
+        KeyFormat::Hex => serde_json::to_string_pretty(&KeysHex::from(keys)),
+        KeyFormat::Bytes => serde_json::to_string_pretty(&keys),
+    }
+    .map(Zeroizing::new)
+    .map_err(|err| format!("Failed to serialize keys: {:?}", err))?;
+
+    std::fs::write(&args.output, output.as_bytes())
+        .map_err(|err| format!("Failed to write {}: {:?}", args.output, err))?;
+    println!("Keys written to {}", args.output);
+
+    Ok(())
+}
+
+/// Handle a single client connection: read its public key, encrypt a
+/// freshly generated ed25519 key pair with it and send both halves back.
+///
+/// Every message is a `send_message` frame: a little-endian u64 length
+/// followed by that many bytes.
+///
+/// ```text
+/// client -> server  [len = 32][client ECIES public key]
+/// server -> client  [len][ECIES(ed25519 public key)]
+/// server -> client  [len][ECIES(ed25519 private key)]
+/// ```
+fn handle_connection(fd: RawFd) -> Result<(), String> {
+    let buf = expect_exact(fd, PUBLIC_KEY_LENGTH as u64)?;
+
+    let mut csprng = OsRng {};
+    let (ed_private_key, ed_public_key) = generate_ed25519_keypair();
+    let ed_private_key = Zeroizing::new(ed_private_key);
+
+    let received_public_key = ecies_ed25519::PublicKey::from_bytes(buf.as_slice())
+        .map_err(|err| format!("Invalid client public key: {:?}", err))?;
+
+    let encrypted_1 = ecies_ed25519::encrypt(&received_public_key, &ed_public_key, &mut csprng)
+        .map_err(|err| format!("Failed to encrypt public key: {:?}", err))?;
+    let encrypted_2 = ecies_ed25519::encrypt(&received_public_key, &ed_private_key, &mut csprng)
+        .map_err(|err| format!("Failed to encrypt private key: {:?}", err))?;
+
+    debug!("Client public key: {}", hex::encode(&received_public_key));
+    debug!(
+        "ED25519 generated public key: {}",
+        hex::encode(&ed_public_key)
+    );
+    debug!("ED25519 encrypted private key: {}", redact(&encrypted_2));
+    trace!(
+        "ED25519 encrypted private key: {}",
+        hex::encode(&encrypted_2)
+    );
+    debug!(
+        "ED25519 encrypted public key: {}",
+        hex::encode(&encrypted_1)
+    );
+
+    send_message(fd, &encrypted_1)?;
+    send_message(fd, &encrypted_2)?;
+
+    Ok(())
+}
+
+/// Hand out enclave key pairs over vsock, one `handle_connection` per
+/// client, until SIGTERM or the accept deadline
 pub fn server(args: ServerArgs) -> Result<(), String> {
+    proto_helpers::check_version()?;
     let socket_fd = socket(
         AddressFamily::Vsock,
         SockType::Stream,
@@ -396,45 +2063,895 @@ pub fn server(args: ServerArgs) -> Result<(), String> {
         None,
     )
     .map_err(|err| format!("Create socket failed: {:?}", err))?;
+    // Owns the listening socket from here on, so it is closed exactly once
+    // on every return path, including failed binds
+    let listener = VsockSocket::new(socket_fd);
 
-    let sockaddr = SockAddr::new_vsock(VMADDR_CID_ANY, args.port);
+    let port = vsock_bind(listener.as_raw_fd(), args.port)?;
+    info!("Server bound to port {}", port);
 
-    bind(socket_fd, &sockaddr).map_err(|err| format!("Bind failed: {:?}", err))?;
+    let enabled = [
+        ("accept-deadline", args.accept_deadline.is_some()),
+        ("no-delay", args.no_delay),
+    ];
+    let features: Vec<&str> = enabled
+        .iter()
+        .filter(|(_, on)| *on)
+        .map(|(name, _)| *name)
+        .collect();
+    info!(
+        "{}",
+        startup_banner("server", &format!("vsock:{}", port), &features, &args)?
+    );
 
-    listen_vsock(socket_fd, BACKLOG).map_err(|err| format!("Listen failed: {:?}", err))?;
+    listen_vsock(listener.as_raw_fd(), BACKLOG)
+        .map_err(|err| format!("Listen failed: {:?}", err))?;
+    // Machine-readable, so scripts started with `--port 0` can find the port
+    println!("LISTENING port={}", port);
+
+    install_shutdown_handler()?;
+    serve_connections(listener.as_raw_fd(), &args, &SHUTDOWN_REQUESTED)
+}
+
+/// The accept loop of `server` on the listening socket `listener`,
+/// returning once `shutdown` is set or the accept deadline passes
+fn serve_connections(
+    listener: RawFd,
+    args: &ServerArgs,
+    shutdown: &AtomicBool,
+) -> Result<(), String> {
+    let limiter = Arc::new(ConnectionLimiter::new(args.max_connections));
 
     loop {
+        // Wait for a free slot before accepting, so excess clients
+        // queue in the listen backlog instead of spawning threads
+        let permit = match limiter.acquire(shutdown) {
+            Some(permit) => permit,
+            None => {
+                info!("Received SIGTERM, shutting down");
+                return Ok(());
+            }
+        };
+        let deadline = args
+            .accept_deadline
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+
+        // SIGTERM may be delivered to any thread, so rather than relying
+        // on EINTR the accept wakes up regularly to check for it
+        let fd = loop {
+            if shutdown.load(Ordering::SeqCst) {
+                info!("Received SIGTERM, shutting down");
+                return Ok(());
+            }
+
+            let wake = Instant::now() + SHUTDOWN_POLL_INTERVAL;
+            match accept_with_deadline(listener, deadline.map_or(wake, |d| d.min(wake))) {
+                Ok(fd) => break fd,
+                Err(AcceptError::DeadlineExpired) => match (args.accept_deadline, deadline) {
+                    (Some(secs), Some(deadline)) if Instant::now() >= deadline => {
+                        info!("No connection within {}s, shutting down", secs);
+                        return Ok(());
+                    }
+                    _ => continue,
+                },
+                Err(err @ AcceptError::Recoverable(_)) => {
+                    warn!("{}, retrying", err);
+                    // Gives in-flight connections time to release descriptors
+                    std::thread::sleep(SHUTDOWN_POLL_INTERVAL);
+                    continue;
+                }
+                Err(err) => return Err(err.to_string()),
+            }
+        };
+
+        // Shut down and closed when the handling thread is done with it
+        let connection = VsockSocket::new(fd);
+
+        // A peer that stops sending would otherwise hold this thread and
+        // its connection slot forever
+        let recv_timeout = Duration::from_secs(args.recv_timeout_secs);
+        if let Err(e) = set_recv_timeout(connection.as_raw_fd(), recv_timeout) {
+            error!("{}, dropping connection", e);
+            continue;
+        }
+
+        if args.no_delay {
+            match set_no_delay(connection.as_raw_fd()) {
+                Ok(true) => {}
+                Ok(false) => debug!("--no-delay is not supported on this socket"),
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        std::thread::spawn(move || {
+            let _permit = permit;
+            handle_connection(connection.as_raw_fd())
+                .unwrap_or_else(|e| error!("Failed to handle connection: {}", e));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::sys::socket::UnixAddr;
+    use rocket::http::Header;
+    use rocket::local::Client;
+
+    fn demo_payload(owner: &(PrivateKey, PublicKey), delegatee_public_key: &PublicKey) -> Payload {
+        let (owner_x, owner_y) = owner.1.bytes_x_y();
+        let (delegatee_x, delegatee_y) = delegatee_public_key.bytes_x_y();
+        serde_json::from_value(serde_json::json!({
+            "initial_private_key": hex::encode(owner.0.bytes()),
+            "initial_public_key_x": hex::encode(owner_x),
+            "initial_public_key_y": hex::encode(owner_y),
+            "delegatee_public_key_x": hex::encode(delegatee_x),
+            "delegatee_public_key_y": hex::encode(delegatee_y),
+            "resource": [],
+        }))
+        .unwrap()
+    }
+
+    fn client_with(extra: &[&str]) -> Client {
+        let mut argv = vec!["app", "client", "--http", "--cid", "3", "--port", "5005"];
+        argv.extend_from_slice(extra);
+        let matches = crate::create_app!().get_matches_from(argv);
+        let args = ClientArgs::new_with(matches.subcommand_matches("client").unwrap()).unwrap();
+        Client::new(build_rocket(&args).unwrap()).unwrap()
+    }
+
+    fn fetch_body() -> String {
+        let (_, delegatee_public_key) = Recrypt::new().generate_key_pair().unwrap();
+        fetch_body_for(&delegatee_public_key)
+    }
+
+    fn fetch_body_for(delegatee_public_key: &PublicKey) -> String {
+        let owner = Recrypt::new().generate_key_pair().unwrap();
+        let payload = demo_payload(&owner, delegatee_public_key);
+        serde_json::to_string(&payload).unwrap()
+    }
+
+    #[test]
+    fn fetch_content_writes_one_audit_entry() {
+        let path = std::env::temp_dir().join(format!("audit-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        let client = client_with(&["--audit-log", path]);
+
+        let response = client
+            .post("/fetch-content")
+            .header(ContentType::JSON)
+            .body(fetch_body())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let log = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.contains("\"transform\""));
+    }
+
+    #[test]
+    fn uploaded_content_is_returned_unchanged() {
+        let client = client_with(&[]);
+        let mut fetched = client
+            .post("/fetch-content?verbose=true")
+            .header(ContentType::JSON)
+            .body(fetch_body())
+            .dispatch();
+        assert_eq!(fetched.status(), Status::Ok);
+        let encrypted: EncryptedResponse =
+            serde_json::from_str(&fetched.body_string().unwrap()).unwrap();
+
+        let mut uploaded = client
+            .post("/upload-content")
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&encrypted).unwrap())
+            .dispatch();
+        assert_eq!(uploaded.status(), Status::Ok);
+        let upload: UploadResponse =
+            serde_json::from_str(&uploaded.body_string().unwrap()).unwrap();
+
+        let mut stored = client.get(format!("/content/{}", upload.id)).dispatch();
+        assert_eq!(stored.status(), Status::Ok);
+        let stored: EncryptedResponse =
+            serde_json::from_str(&stored.body_string().unwrap()).unwrap();
+        assert_eq!(stored, encrypted);
+    }
+
+    #[test]
+    fn decrypt_recovers_the_demo_plaintext() {
+        let client = client_with(&[]);
+        let (delegatee_private_key, delegatee_public_key) =
+            Recrypt::new().generate_key_pair().unwrap();
+
+        let mut fetched = client
+            .post("/fetch-content")
+            .header(ContentType::JSON)
+            .body(fetch_body_for(&delegatee_public_key))
+            .dispatch();
+        assert_eq!(fetched.status(), Status::Ok);
+        let fetched: TransformedObjectResponse =
+            serde_json::from_str(&fetched.body_string().unwrap()).unwrap();
+
+        let body = serde_json::json!({
+            "delegatee_private_key": hex::encode(delegatee_private_key.bytes()),
+            "transformed_object": fetched.transformed_object,
+        });
+        let mut decrypted = client
+            .post("/decrypt")
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch();
+        assert_eq!(decrypted.status(), Status::Ok);
+        let decrypted: DecryptResponse =
+            serde_json::from_str(&decrypted.body_string().unwrap()).unwrap();
+
+        let plaintext = hardcoded_plaintext(&CryptoParams::detect().unwrap()).unwrap();
+        assert_eq!(decrypted.plaintext.0[..], plaintext.bytes()[..]);
+    }
+
+    /// Upload a verbose `/fetch-content` response for `resource`,
+    /// transformed to `delegatee_public_key`, and return its id
+    fn upload_resource(
+        client: &Client,
+        delegatee_public_key: &PublicKey,
+        resource: &[u8],
+    ) -> String {
+        let owner = Recrypt::new().generate_key_pair().unwrap();
+        let mut payload = demo_payload(&owner, delegatee_public_key);
+        payload.resource = resource.to_vec();
+        let mut fetched = client
+            .post("/fetch-content?verbose=true")
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&payload).unwrap())
+            .dispatch();
+        assert_eq!(fetched.status(), Status::Ok);
+
+        let mut uploaded = client
+            .post("/upload-content")
+            .header(ContentType::JSON)
+            .body(fetched.body_string().unwrap())
+            .dispatch();
+        assert_eq!(uploaded.status(), Status::Ok);
+        let upload: UploadResponse =
+            serde_json::from_str(&uploaded.body_string().unwrap()).unwrap();
+        upload.id
+    }
+
+    fn decrypt_resource_body(delegatee_private_key: &PrivateKey) -> String {
+        serde_json::json!({
+            "delegatee_private_key": hex::encode(delegatee_private_key.bytes()),
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn stored_resource_decrypts_to_the_original() {
+        let client = client_with(&[]);
+        let recrypt = Recrypt::new();
+        let (delegatee_private_key, delegatee_public_key) = recrypt.generate_key_pair().unwrap();
+        let resource = recrypt.gen_plaintext();
+        let id = upload_resource(&client, &delegatee_public_key, &resource.bytes()[..]);
+
+        let mut decrypted = client
+            .post(format!("/resources/{}/decrypt", id))
+            .header(ContentType::JSON)
+            .body(decrypt_resource_body(&delegatee_private_key))
+            .dispatch();
+        assert_eq!(decrypted.status(), Status::Ok);
+        let decrypted: DecryptResponse =
+            serde_json::from_str(&decrypted.body_string().unwrap()).unwrap();
+        assert_eq!(decrypted.plaintext.0[..], resource.bytes()[..]);
+    }
+
+    #[test]
+    fn unknown_resource_id_is_not_found() {
+        let client = client_with(&[]);
+        let (delegatee_private_key, _) = Recrypt::new().generate_key_pair().unwrap();
+
+        let response = client
+            .post(format!("/resources/{}/decrypt", new_resource_id()))
+            .header(ContentType::JSON)
+            .body(decrypt_resource_body(&delegatee_private_key))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn stored_resource_rejects_the_wrong_delegatee_key() {
+        let client = client_with(&[]);
+        let recrypt = Recrypt::new();
+        let (_, delegatee_public_key) = recrypt.generate_key_pair().unwrap();
+        let (other_private_key, _) = recrypt.generate_key_pair().unwrap();
+        let id = upload_resource(&client, &delegatee_public_key, &[]);
+
+        let response = client
+            .post(format!("/resources/{}/decrypt", id))
+            .header(ContentType::JSON)
+            .body(decrypt_resource_body(&other_private_key))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    fn server_args(extra: &[&str]) -> ServerArgs {
+        let mut argv = vec!["app", "server", "--port", "5005"];
+        argv.extend_from_slice(extra);
+        let matches = crate::create_app!().get_matches_from(argv);
+        ServerArgs::new_with(matches.subcommand_matches("server").unwrap()).unwrap()
+    }
+
+    /// A listening Unix socket standing in for the vsock listener
+    fn unix_listener(name: &str) -> (RawFd, SockAddr, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("{}-{}.sock", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let addr = SockAddr::Unix(UnixAddr::new(path.as_path()).unwrap());
+        let fd = socket(
+            AddressFamily::Unix,
+            SockType::Stream,
+            SockFlag::empty(),
+            None,
+        )
+        .unwrap();
+        bind(fd, &addr).unwrap();
+        listen_vsock(fd, BACKLOG).unwrap();
+        (fd, addr, path)
+    }
+
+    fn unix_connect(addr: &SockAddr) -> RawFd {
+        let fd = socket(
+            AddressFamily::Unix,
+            SockType::Stream,
+            SockFlag::empty(),
+            None,
+        )
+        .unwrap();
+        connect(fd, addr).unwrap();
+        fd
+    }
+
+    #[test]
+    fn limiter_stops_waiting_once_shutdown_is_requested() {
+        let limiter = Arc::new(ConnectionLimiter::new(1));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let _held = limiter.acquire(&shutdown).unwrap();
+
+        let setter = Arc::clone(&shutdown);
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            setter.store(true, Ordering::SeqCst);
+        });
+
+        let started = Instant::now();
+        assert!(limiter.acquire(&shutdown).is_none());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
 
-        // Read Key Generation Request
+    #[test]
+    fn connection_beyond_the_limit_waits_for_a_free_slot() {
+        let (listener, addr, path) = unix_listener("limit");
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server = {
+            let shutdown = Arc::clone(&shutdown);
+            std::thread::spawn(move || {
+                let args = server_args(&["--max-vsock-connections", "1"]);
+                serve_connections(listener, &args, &shutdown)
+            })
+        };
+
+        // Takes the only slot: the server waits for its public key
+        let first = unix_connect(&addr);
+        let second = unix_connect(&addr);
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || sender.send(request_enclave_keys(second)).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+
+        close(first).unwrap();
+        let keys = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(keys.public_key.0.len(), PUBLIC_KEY_LENGTH);
 
-        // Read Encryption Request
+        shutdown.store(true, Ordering::SeqCst);
+        server.join().unwrap().unwrap();
+        close(second).unwrap();
+        close(listener).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
 
+    #[test]
+    fn sigterm_stops_the_server_within_a_second() {
+        let (listener, _, path) = unix_listener("sigterm");
+        install_shutdown_handler().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let args = server_args(&[]);
+            let result = serve_connections(listener, &args, &SHUTDOWN_REQUESTED);
+            sender.send(result).unwrap();
+        });
+
+        std::thread::sleep(Duration::from_millis(100));
+        nix::sys::signal::raise(Signal::SIGTERM).unwrap();
+        let result = receiver.recv_timeout(Duration::from_secs(1));
+
+        SHUTDOWN_REQUESTED.store(false, Ordering::SeqCst);
+        close(listener).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(result, Ok(Ok(())));
+    }
 
+    #[test]
+    fn startup_banner_has_the_version_but_not_the_api_key() {
+        let matches = crate::create_app!().get_matches_from(vec![
+            "app",
+            "client",
+            "--http",
+            "--cid",
+            "3",
+            "--port",
+            "5005",
+            "--api-key",
+            "banner-secret",
+        ]);
+        let args = ClientArgs::new_with(matches.subcommand_matches("client").unwrap()).unwrap();
+
+        let banner = startup_banner("client", "127.0.0.1:8000", &["api-key"], &args).unwrap();
+        assert!(banner.contains(&format!("\"version\":\"{}\"", env!("CARGO_PKG_VERSION"))));
+        assert!(!banner.contains("banner-secret"));
+    }
 
+    #[test]
+    fn backoff_delay_stays_within_the_jitter_bounds_and_cap() {
+        let mut rng = ChaCha20Rng::seed_from_u64(803);
+        let base = Duration::from_millis(100);
+        let max_attempts = 5;
+        let cap = doubled(base, max_attempts - 2);
+
+        for attempt in 0..max_attempts - 1 {
+            let unjittered = doubled(base, attempt);
+            for _ in 0..1000 {
+                let delay = backoff_delay(&mut rng, base, attempt, max_attempts);
+                assert!(delay >= unjittered.mul_f64(0.5).min(cap));
+                assert!(delay < unjittered.mul_f64(1.5));
+                assert!(delay <= cap);
+            }
+        }
+    }
 
+    #[test]
+    fn doubled_saturates_at_the_maximum_backoff() {
+        assert_eq!(
+            doubled(Duration::from_millis(100), 3),
+            Duration::from_millis(800)
+        );
+        assert_eq!(doubled(Duration::from_secs(1), 64), MAX_CONNECT_BACKOFF);
+        assert_eq!(doubled(Duration::from_secs(200), 1), MAX_CONNECT_BACKOFF);
+    }
 
-        let fd = accept(socket_fd).map_err(|err| format!("Accept failed: {:?}", err))?;
+    #[test]
+    fn ecies_ciphertext_decrypts_only_with_the_matching_key() {
+        let mut csprng = OsRng {};
+        let (secret, public) = ecies_ed25519::generate_keypair(&mut csprng);
+        let (other_secret, _) = ecies_ed25519::generate_keypair(&mut csprng);
+        let payload = b"ed25519 private key bytes";
+
+        let encrypted = ecies_ed25519::encrypt(&public, payload, &mut csprng).unwrap();
+        assert_ne!(&encrypted[..], &payload[..]);
+        assert_eq!(
+            ecies_ed25519::decrypt(&secret, &encrypted).unwrap(),
+            payload.to_vec()
+        );
+        assert!(ecies_ed25519::decrypt(&other_secret, &encrypted).is_err());
+    }
 
-        let len = recv_u64(fd)?;
-        let mut buf = [0u8; BUF_MAX_LEN];
-        recv_loop(fd, &mut buf, len)?;
+    #[test]
+    fn enclave_keys_decrypt_to_a_matching_key_pair() {
+        let (client, server) = nix::sys::socket::socketpair(
+            AddressFamily::Unix,
+            SockType::Stream,
+            None,
+            SockFlag::empty(),
+        )
+        .unwrap();
+        let handler = std::thread::spawn(move || handle_connection(server));
 
-        let mut csprng = OsRng{};
-        let keypair: Keypair = Keypair::generate(&mut csprng);
+        let keys = request_enclave_keys(client).unwrap();
+        handler.join().unwrap().unwrap();
+        close(client).unwrap();
+        close(server).unwrap();
 
-        let ed_public_key = keypair.public.as_bytes();
-        let ed_private_key = keypair.secret.as_bytes();
+        let secret = ed25519_dalek::SecretKey::from_bytes(&keys.private_key).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        assert_eq!(public.as_bytes()[..], keys.public_key[..]);
+    }
 
-        let received_public_key =  ecies_ed25519::PublicKey::from_bytes(&buf.as_slice()).unwrap();
+    #[test]
+    fn handle_connection_sends_two_ecies_frames() {
+        let (client, server) = nix::sys::socket::socketpair(
+            AddressFamily::Unix,
+            SockType::Stream,
+            None,
+            SockFlag::empty(),
+        )
+        .unwrap();
+        let (_, public) = ecies_ed25519::generate_keypair(&mut OsRng {});
+        send_message(client, public.as_bytes()).unwrap();
+
+        handle_connection(server).unwrap();
+        close(server).unwrap();
+
+        // ECIES adds a 32 byte ephemeral key, a 12 byte nonce and a
+        // 16 byte tag to each 32 byte ed25519 key
+        let mut reader = MessageReader::new(client, MAX_KEY_MESSAGE_LEN);
+        assert_eq!(reader.next_message().unwrap().unwrap().len(), 92);
+        assert_eq!(reader.next_message().unwrap().unwrap().len(), 92);
+        assert_eq!(reader.next_message().unwrap(), None);
+        close(client).unwrap();
+    }
 
-        let encrypted_1 = ecies_ed25519::encrypt(&received_public_key, ed_public_key, &mut csprng).unwrap();
-        let encrypted_2 = ecies_ed25519::encrypt(&received_public_key, ed_private_key, &mut csprng).unwrap();
+    fn open_fd_count() -> usize {
+        std::fs::read_dir("/proc/self/fd").unwrap().count()
+    }
+
+    #[test]
+    fn served_connections_do_not_leak_descriptors() {
+        const CONNECTIONS: usize = 64;
+        let (listener, addr, path) = unix_listener("fd-leak");
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server = {
+            let shutdown = Arc::clone(&shutdown);
+            std::thread::spawn(move || serve_connections(listener, &server_args(&[]), &shutdown))
+        };
+
+        let before = open_fd_count();
+        for _ in 0..CONNECTIONS {
+            let client = unix_connect(&addr);
+            request_enclave_keys(client).unwrap();
+            close(client).unwrap();
+        }
+        // Lets the last handler threads drop their connections
+        std::thread::sleep(Duration::from_millis(200));
+        let after = open_fd_count();
+
+        shutdown.store(true, Ordering::SeqCst);
+        server.join().unwrap().unwrap();
+        close(listener).unwrap();
+        std::fs::remove_file(path).unwrap();
+        // Other tests open descriptors concurrently, so allow some slack;
+        // a leak would add one per connection
+        assert!(
+            after < before + CONNECTIONS / 2,
+            "{} descriptors before, {} after",
+            before,
+            after
+        );
+    }
 
-        println!("Received clients public key in bytes  {:?}", buf.clone());
-        println!("Clients Public Key  {:?}", hex::encode(&received_public_key));
+    #[test]
+    fn root_response_carries_the_cors_header() {
+        let origin = "https://demo.example.com";
+        for client in [client_with(&[]), client_with(&["--cors-origin", origin])] {
+            let response = client
+                .get("/")
+                .header(Header::new("Origin", origin))
+                .dispatch();
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(
+                response.headers().get_one("Access-Control-Allow-Origin"),
+                Some(origin)
+            );
+        }
+    }
 
-        println!("ED25519 Generated Public Key {:?}", hex::encode(&ed_public_key));
-        println!("ED25519 Encrypted private key key with Clients Public Key {:?} ", hex::encode(&encrypted_2));
-        println!("ED25519 Encrypted public key with Clients Public Key  {:?}", hex::encode(&encrypted_1));
+    #[test]
+    fn post_routes_answer_cors_preflights() {
+        let client = client_with(&[]);
+        let response = client
+            .options("/fetch-content")
+            .header(Header::new("Origin", "https://demo.example.com"))
+            .header(Header::new("Access-Control-Request-Method", "POST"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let methods = response
+            .headers()
+            .get_one("Access-Control-Allow-Methods")
+            .unwrap();
+        assert!(methods.contains("POST"));
+    }
+
+    #[test]
+    fn api_key_is_required_on_protected_routes_only() {
+        let client = client_with(&["--api-key", "s3cret"]);
+
+        let response = client.get("/get-keys").dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        let response = client
+            .get("/get-keys")
+            .header(Header::new("X-Api-Key", "wrong"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let response = client
+            .get("/get-keys")
+            .header(Header::new("X-Api-Key", "s3cret"))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        assert_eq!(client.get("/").dispatch().status(), Status::Ok);
+        assert_eq!(client.get("/health").dispatch().status(), Status::Ok);
+    }
+
+    #[test]
+    fn missing_api_key_is_rejected_before_the_body_is_read() {
+        let client = client_with(&["--api-key", "s3cret"]);
+        let mut response = client
+            .post("/fetch-content")
+            .header(ContentType::JSON)
+            .body("{not json")
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert!(response
+            .body_string()
+            .unwrap()
+            .contains("missing X-Api-Key header"));
+    }
+
+    #[test]
+    fn health_reports_status_version_and_uptime() {
+        let client = client_with(&[]);
+        let mut response = client.get("/health").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let health: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(health["status"], "ok");
+        assert_eq!(health["version"], env!("CARGO_PKG_VERSION"));
+        assert!(health["uptime_secs"].is_u64());
+    }
+
+    #[test]
+    fn fetch_content_status_codes() {
+        let client = client_with(&[]);
+
+        let response = client
+            .post("/fetch-content")
+            .header(ContentType::JSON)
+            .body("{not json")
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let mut short_key: serde_json::Value = serde_json::from_str(&fetch_body()).unwrap();
+        short_key["initial_public_key_x"] = serde_json::json!("0102");
+        let response = client
+            .post("/fetch-content")
+            .header(ContentType::JSON)
+            .body(short_key.to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let mut response = client
+            .post("/fetch-content")
+            .header(ContentType::JSON)
+            .body(fetch_body())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let fetched: TransformedObjectResponse =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert!(!fetched.transformed_object.is_empty());
+    }
+
+    fn fetched_transformed_object(client: &Client) -> String {
+        let mut fetched = client
+            .post("/fetch-content")
+            .header(ContentType::JSON)
+            .body(fetch_body())
+            .dispatch();
+        assert_eq!(fetched.status(), Status::Ok);
+        let fetched: TransformedObjectResponse =
+            serde_json::from_str(&fetched.body_string().unwrap()).unwrap();
+        fetched.transformed_object
+    }
+
+    fn verify(client: &Client, transformed_object: &str) -> VerifyResponse {
+        let body = serde_json::json!({ "transformed_object": transformed_object });
+        let mut response = client
+            .post("/verify")
+            .header(ContentType::JSON)
+            .body(body.to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        serde_json::from_str(&response.body_string().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn verify_accepts_a_fetched_object_and_audits_it() {
+        let path = std::env::temp_dir().join(format!("verify-audit-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        let client = client_with(&["--audit-log", path, "--crypto-timeout", "30"]);
+
+        let transformed_object = fetched_transformed_object(&client);
+        assert!(verify(&client, &transformed_object).valid);
+
+        let log = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(log.lines().any(|line| line.contains("\"verify\"")));
+    }
+
+    #[test]
+    fn verify_rejects_a_tampered_signature() {
+        let client = client_with(&[]);
+        let mut tfo = tfo_from_hex(&fetched_transformed_object(&client)).unwrap();
+        tfo.ed25519_signature[0] ^= 0x01;
+
+        let tampered = crate::proto_helpers::tfo_to_hex(&tfo).unwrap();
+        assert!(!verify(&client, &tampered).valid);
+    }
+
+    #[test]
+    fn upload_with_an_empty_resource_is_rejected() {
+        let client = client_with(&[]);
+        let mut fetched = client
+            .post("/fetch-content?verbose=true")
+            .header(ContentType::JSON)
+            .body(fetch_body())
+            .dispatch();
+        let mut encrypted: EncryptedResponse =
+            serde_json::from_str(&fetched.body_string().unwrap()).unwrap();
+        encrypted.transformed = HexBytes::default();
+
+        let mut response = client
+            .post("/upload-content")
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&encrypted).unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response
+            .body_string()
+            .unwrap()
+            .contains("resource must not be empty"));
+    }
+
+    #[test]
+    fn unchunk_strips_the_padding_of_the_last_block() {
+        let data: Vec<u8> = (0..40).collect();
+        let plaintexts = chunk_into_plaintexts(&data).unwrap();
+        assert_eq!(plaintexts.len(), 1);
+        // 40 bytes fill one coordinate and part of the next
+        assert_eq!(plaintexts[0].bytes()[..2], [0, 0]);
+        assert_eq!(plaintexts[0].bytes()[32..34], [0, 31]);
+
+        assert_eq!(*unchunk_plaintexts(&plaintexts, data.len()).unwrap(), data);
+        assert!(unchunk_plaintexts(&plaintexts, PLAINTEXT_CHUNK_LEN + 1).is_err());
+    }
+
+    #[test]
+    fn chunked_file_spanning_two_blocks_decrypts_to_the_original() {
+        let recrypt = Recrypt::new();
+        let owner = recrypt.generate_key_pair().unwrap();
+        let (delegatee_private_key, delegatee_public_key) = recrypt.generate_key_pair().unwrap();
+        let signing_keypair = recrypt.generate_ed25519_key_pair();
+        let data: Vec<u8> = (0..PLAINTEXT_CHUNK_LEN + 40).map(|i| i as u8).collect();
+
+        let chunks = chunk_into_plaintexts(&data)
+            .unwrap()
+            .into_iter()
+            .map(|plain_text| {
+                let inputs = TransformInputs {
+                    plain_text,
+                    owner_public_key: owner.1,
+                    delegatee_public_key,
+                    transform_key_source: TransformKeySource::Generate(owner.0.clone()),
+                };
+                let (transformed_val, _) =
+                    encrypt_and_transform(&recrypt, inputs, &signing_keypair, false).unwrap();
+                let (to, _) = tfo_from_transformed(transformed_val);
+                TransformedObjectResponse {
+                    transformed_object: hex::encode(to.write_to_bytes().unwrap()),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 2);
+
+        let chunked = ChunkedTransformResponse {
+            original_len: data.len(),
+            chunks,
+        };
+        let decrypted = decrypt_chunked(&recrypt, &chunked, &delegatee_private_key).unwrap();
+        assert_eq!(*decrypted, data);
+    }
+
+    #[test]
+    fn payload_lengths_follow_the_detected_params() {
+        let recrypt = Recrypt::new();
+        let params = CryptoParams::detect().unwrap();
+        let owner = recrypt.generate_key_pair().unwrap();
+        let (_, delegatee_public_key) = recrypt.generate_key_pair().unwrap();
+        let payload = demo_payload(&owner, &delegatee_public_key);
+        assert!(payload.validate(&params).is_ok());
+
+        let wider = CryptoParams {
+            public_key_len: params.public_key_len + 1,
+            ..params
+        };
+        assert_eq!(
+            payload.validate(&wider),
+            Err(format!(
+                "initial_public_key_x must be {} bytes, got {}",
+                wider.public_key_len, params.public_key_len
+            ))
+        );
+    }
+
+    #[test]
+    fn transform_rejects_a_flipped_signature_before_transforming() {
+        let recrypt = Recrypt::new();
+        let signing_keypair = recrypt.generate_ed25519_key_pair();
+        let (owner_private_key, owner_public_key) = recrypt.generate_key_pair().unwrap();
+        let (_, delegatee_public_key) = recrypt.generate_key_pair().unwrap();
+        let transform_key = recrypt
+            .generate_transform_key(&owner_private_key, &delegatee_public_key, &signing_keypair)
+            .unwrap();
+        let encrypted = recrypt
+            .encrypt(
+                &recrypt.gen_plaintext(),
+                &owner_public_key,
+                &signing_keypair,
+            )
+            .unwrap();
+
+        let tampered = match encrypted {
+            EncryptedValue::EncryptedOnceValue {
+                ephemeral_public_key,
+                encrypted_message,
+                auth_hash,
+                public_signing_key,
+                signature,
+            } => {
+                let mut bytes = *signature.bytes();
+                bytes[0] ^= 1;
+                EncryptedValue::EncryptedOnceValue {
+                    ephemeral_public_key,
+                    encrypted_message,
+                    auth_hash,
+                    public_signing_key,
+                    signature: Ed25519Signature::new(bytes),
+                }
+            }
+            EncryptedValue::TransformedValue { .. } => unreachable!("encrypted once"),
+        };
+
+        let err =
+            verified_transform(&recrypt, tampered, transform_key, &signing_keypair).unwrap_err();
+        assert_eq!(err.status, Status::BadRequest);
+        assert!(err.error.starts_with("Signature verification failed"));
+    }
+
+    #[test]
+    fn verbose_response_honours_encoding_and_transform_key() {
+        let client = client_with(&["--allow-transform-key-disclosure"]);
+        let mut fetched = client
+            .post("/fetch-content?verbose=true&encoding=base64&include_transform_key=true")
+            .header(ContentType::JSON)
+            .body(fetch_body())
+            .dispatch();
+        assert_eq!(fetched.status(), Status::Ok);
+        let encrypted: EncryptedResponse =
+            serde_json::from_str(&fetched.body_string().unwrap()).unwrap();
+        assert_eq!(
+            encrypted.transformed_object,
+            Some(base64::encode(&encrypted.transformed[..]))
+        );
+        assert!(encrypted.transform_key.is_some());
+
+        let mut fetched = client
+            .post("/fetch-content?verbose=true")
+            .header(ContentType::JSON)
+            .body(fetch_body())
+            .dispatch();
+        let encrypted: EncryptedResponse =
+            serde_json::from_str(&fetched.body_string().unwrap()).unwrap();
+        assert_eq!(encrypted.transformed_object, None);
+        assert_eq!(encrypted.transform_key, None);
     }
 }
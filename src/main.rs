@@ -1,23 +1,88 @@
 use clap::{App, AppSettings, Arg, SubCommand};
 
-use proxy_reencyption_enclave_app::command_parser::{ClientArgs, ServerArgs};
+use proxy_reencyption_enclave_app::bench::bench;
+use proxy_reencyption_enclave_app::command_parser::{
+    parse_log_format, parse_log_level, print_config, BenchArgs, ClientArgs, CompletionsArgs,
+    ConvertKeysArgs, Ed25519KeygenArgs, EncryptArgs, FetchSigningKeyArgs, KeygenArgs, ServerArgs,
+    TransformKeyArgs, VerifySigningKeyArgs,
+};
 use proxy_reencyption_enclave_app::create_app;
-use proxy_reencyption_enclave_app::utils::ExitGracefully;
-use proxy_reencyption_enclave_app::{client, server};
+use proxy_reencyption_enclave_app::signing_key::{fetch_signing_key, verify_signing_key};
+use proxy_reencyption_enclave_app::utils::{init_logging, ExitGracefully};
+use proxy_reencyption_enclave_app::{
+    client, convert_keys, ed25519_keygen, keygen, run_encrypt, run_transform_key, server,
+};
 
 fn main() {
     let app = create_app!();
     let args = app.get_matches();
 
+    let log_level = parse_log_level(&args).ok_or_exit_code(args.usage(), 2);
+    let log_format = parse_log_format(&args).ok_or_exit_code(args.usage(), 2);
+    init_logging(log_level, log_format).ok_or_exit(args.usage());
+
     match args.subcommand() {
         ("server", Some(args)) => {
-            let server_args = ServerArgs::new_with(args).ok_or_exit(args.usage());
+            let server_args = ServerArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            if args.is_present("print-config") {
+                print_config(&server_args).ok_or_exit(args.usage());
+                return;
+            }
             server(server_args).ok_or_exit(args.usage());
         }
         ("client", Some(args)) => {
-            let client_args = ClientArgs::new_with(args).ok_or_exit(args.usage());
+            let client_args = ClientArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            if args.is_present("print-config") {
+                print_config(&client_args).ok_or_exit(args.usage());
+                return;
+            }
             client(client_args).ok_or_exit(args.usage());
         }
+        ("bench", Some(args)) => {
+            let bench_args = BenchArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            if args.is_present("print-config") {
+                print_config(&bench_args).ok_or_exit(args.usage());
+                return;
+            }
+            bench(bench_args).ok_or_exit(args.usage());
+        }
+        ("fetch-signing-key", Some(args)) => {
+            let fetch_args = FetchSigningKeyArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            fetch_signing_key(fetch_args).ok_or_exit(args.usage());
+        }
+        ("verify-signing-key", Some(args)) => {
+            let verify_args = VerifySigningKeyArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            verify_signing_key(verify_args).ok_or_exit(args.usage());
+        }
+        ("convert-keys", Some(args)) => {
+            let convert_args = ConvertKeysArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            convert_keys(convert_args).ok_or_exit(args.usage());
+        }
+        ("encrypt", Some(args)) => {
+            let encrypt_args = EncryptArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            run_encrypt(encrypt_args).ok_or_exit(args.usage());
+        }
+        ("transform-key", Some(args)) => {
+            let transform_key_args =
+                TransformKeyArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            run_transform_key(transform_key_args).ok_or_exit(args.usage());
+        }
+        ("keygen", Some(args)) => {
+            let keygen_args = KeygenArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            keygen(keygen_args).ok_or_exit(args.usage());
+        }
+        ("ed25519-keygen", Some(args)) => {
+            let keygen_args = Ed25519KeygenArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            ed25519_keygen(keygen_args).ok_or_exit(args.usage());
+        }
+        ("completions", Some(args)) => {
+            let completions_args = CompletionsArgs::new_with(args).ok_or_exit_code(args.usage(), 2);
+            create_app!().gen_completions_to(
+                env!("CARGO_BIN_NAME"),
+                completions_args.shell,
+                &mut std::io::stdout(),
+            );
+        }
         (&_, _) => {}
     }
 }
@@ -1,21 +1,349 @@
-use serde::{Deserialize, Serialize};
+use crate::proto::transform::{PublicKey as PPK, TransformBlock as TFB, TransformObject as TFO};
+use crate::CryptoParams;
+use protobuf::{Message, MessageField};
+use recrypt::api::{
+    Ed25519Signature, EncryptedTempKey, HashedValue, PublicKey, PublicSigningKey, RecryptErr,
+    TransformKey,
+};
+use rocket::http::{RawStr, Status};
+use rocket::request::FromFormValue;
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket_contrib::json::Json;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Clone)]
+/// Byte string that travels over the wire as a hex-encoded string
+#[derive(Debug, PartialEq, Eq, Clone, Default, Zeroize)]
+pub struct HexBytes(pub Vec<u8>);
+
+impl Deref for HexBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for HexBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        HexBytes(bytes)
+    }
+}
+
+impl From<&[u8]> for HexBytes {
+    fn from(bytes: &[u8]) -> Self {
+        HexBytes(bytes.to_vec())
+    }
+}
+
+/// Private key bytes, zeroed on drop and redacted from `Debug` output.
+/// Serialized exactly like a `Vec<u8>`.
+#[derive(PartialEq, Eq, Clone, Default, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+#[serde(transparent)]
+pub struct SecretBytes(pub Vec<u8>);
+
+impl Deref for SecretBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+}
+
+impl From<&[u8]> for SecretBytes {
+    fn from(bytes: &[u8]) -> Self {
+        SecretBytes(bytes.to_vec())
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {}])", self.0.len())
+    }
+}
+
+impl AsRef<[u8]> for SecretBytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Serialize for HexBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hex_bytes::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for HexBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        hex_bytes::deserialize(deserializer).map(HexBytes)
+    }
+}
+
+/// Serde helpers for byte fields written as hex strings, for use with
+/// `#[serde(with = "hex_bytes")]` on `Vec<u8>` or `SecretBytes` fields
+pub mod hex_bytes {
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use zeroize::Zeroizing;
+
+    pub fn serialize<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: AsRef<[u8]>,
+        S: Serializer,
+    {
+        serializer.serialize_str(&Zeroizing::new(hex::encode(bytes)))
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        // The string may hold a private key
+        let s = Zeroizing::new(String::deserialize(deserializer)?);
+        hex::decode(&*s).map(T::from).map_err(D::Error::custom)
+    }
+}
+
+/// Byte order of the public key coordinates returned to clients.
+/// recrypt encodes coordinates big-endian, so `Be` is the native order
+/// and `Le` reverses each coordinate.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CoordEndian {
+    Be,
+    Le,
+}
+
+impl Default for CoordEndian {
+    fn default() -> Self {
+        CoordEndian::Be
+    }
+}
+
+impl CoordEndian {
+    /// Convert a coordinate from recrypt's order into this order.
+    /// Applying it twice gives back the original bytes.
+    pub fn apply(self, coordinate: &[u8]) -> Vec<u8> {
+        match self {
+            CoordEndian::Be => coordinate.to_vec(),
+            CoordEndian::Le => coordinate.iter().rev().copied().collect(),
+        }
+    }
+}
+
+impl<'v> FromFormValue<'v> for CoordEndian {
+    type Error = &'v RawStr;
+
+    fn from_form_value(value: &'v RawStr) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "be" => Ok(CoordEndian::Be),
+            "le" => Ok(CoordEndian::Le),
+            _ => Err(value),
+        }
+    }
+}
+
+/// How `/fetch-content` encodes `transformed_object` in JSON responses
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OutputEncoding {
+    Hex,
+    Base64,
+}
+
+impl Default for OutputEncoding {
+    fn default() -> Self {
+        OutputEncoding::Hex
+    }
+}
+
+impl OutputEncoding {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "hex" => Ok(OutputEncoding::Hex),
+            "base64" => Ok(OutputEncoding::Base64),
+            _ => Err(format!("encoding must be hex or base64, got {}", value)),
+        }
+    }
+
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            OutputEncoding::Hex => hex::encode(bytes),
+            OutputEncoding::Base64 => base64::encode(bytes),
+        }
+    }
+}
+
+impl<'v> FromFormValue<'v> for OutputEncoding {
+    type Error = String;
+
+    fn from_form_value(value: &'v RawStr) -> Result<Self, Self::Error> {
+        OutputEncoding::parse(value.as_str())
+    }
+}
+
+/// 32 byte seed for deterministic key generation, given as 64 hex
+/// characters. Anyone holding the seed can regenerate the private key,
+/// so it is zeroed on drop.
+#[derive(Debug, PartialEq, Eq, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct KeySeed(pub [u8; 32]);
+
+impl KeySeed {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let bytes = hex::decode(value).map_err(|_err| "seed is not valid hex".to_string())?;
+        if bytes.len() != 32 {
+            return Err(format!("seed must be 32 bytes, got {}", bytes.len()));
+        }
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&bytes);
+        Ok(KeySeed(seed))
+    }
+}
+
+impl<'v> FromFormValue<'v> for KeySeed {
+    type Error = String;
+
+    fn from_form_value(value: &'v RawStr) -> Result<Self, Self::Error> {
+        KeySeed::parse(value.as_str())
+    }
+}
+
+/// Byte fields are accepted as byte arrays or hex strings
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 pub struct Payload {
-    pub initial_private_key: Vec<u8>,
+    // Not needed by /fetch-content when `transform_key` is supplied
+    #[serde(default, deserialize_with = "secret_bytes_or_hex")]
+    pub initial_private_key: SecretBytes,
+    #[serde(deserialize_with = "bytes_or_hex")]
+    pub initial_public_key_x: Vec<u8>,
+    #[serde(deserialize_with = "bytes_or_hex")]
+    pub initial_public_key_y: Vec<u8>,
+    #[serde(deserialize_with = "bytes_or_hex")]
+    pub delegatee_public_key_x: Vec<u8>,
+    #[serde(deserialize_with = "bytes_or_hex")]
+    pub delegatee_public_key_y: Vec<u8>,
+    #[serde(deserialize_with = "bytes_or_hex")]
+    pub resource: Vec<u8>,
+    // Transform key as returned with `include_transform_key`, reused
+    // instead of generating one from `initial_private_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform_key: Option<HexBytes>,
+}
+
+impl Payload {
+    /// Check the fields a handler relies on against the key sizes of the
+    /// linked recrypt version. `resource` may be empty: `/fetch-content`
+    /// then encrypts the hardcoded demo plaintext.
+    pub fn validate(&self, params: &CryptoParams) -> Result<(), String> {
+        // The private key is only used when no transform key is supplied
+        if self.transform_key.is_none() {
+            check_len(
+                "initial_private_key",
+                &self.initial_private_key,
+                params.private_key_len,
+            )?;
+        }
+        check_len(
+            "initial_public_key_x",
+            &self.initial_public_key_x,
+            params.public_key_len,
+        )?;
+        check_len(
+            "initial_public_key_y",
+            &self.initial_public_key_y,
+            params.public_key_len,
+        )?;
+        check_len(
+            "delegatee_public_key_x",
+            &self.delegatee_public_key_x,
+            params.public_key_len,
+        )?;
+        check_len(
+            "delegatee_public_key_y",
+            &self.delegatee_public_key_y,
+            params.public_key_len,
+        )?;
+        Ok(())
+    }
+}
+
+/// `Payload` with hex strings instead of byte arrays, for clients such
+/// as curl scripts that find arrays awkward to produce
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct PayloadHex {
+    #[serde(default, with = "hex_bytes")]
+    pub initial_private_key: SecretBytes,
+    #[serde(with = "hex_bytes")]
     pub initial_public_key_x: Vec<u8>,
+    #[serde(with = "hex_bytes")]
     pub initial_public_key_y: Vec<u8>,
+    #[serde(with = "hex_bytes")]
     pub delegatee_public_key_x: Vec<u8>,
+    #[serde(with = "hex_bytes")]
     pub delegatee_public_key_y: Vec<u8>,
+    #[serde(with = "hex_bytes")]
     pub resource: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform_key: Option<HexBytes>,
+}
+
+impl From<Payload> for PayloadHex {
+    fn from(payload: Payload) -> Self {
+        PayloadHex {
+            initial_private_key: payload.initial_private_key,
+            initial_public_key_x: payload.initial_public_key_x,
+            initial_public_key_y: payload.initial_public_key_y,
+            delegatee_public_key_x: payload.delegatee_public_key_x,
+            delegatee_public_key_y: payload.delegatee_public_key_y,
+            resource: payload.resource,
+            transform_key: payload.transform_key,
+        }
+    }
+}
+
+impl From<PayloadHex> for Payload {
+    fn from(payload: PayloadHex) -> Self {
+        Payload {
+            initial_private_key: payload.initial_private_key,
+            initial_public_key_x: payload.initial_public_key_x,
+            initial_public_key_y: payload.initial_public_key_y,
+            delegatee_public_key_x: payload.delegatee_public_key_x,
+            delegatee_public_key_y: payload.delegatee_public_key_y,
+            resource: payload.resource,
+            transform_key: payload.transform_key,
+        }
+    }
+}
+
+fn check_len(field: &str, bytes: &[u8], len: usize) -> Result<(), String> {
+    if bytes.len() != len {
+        return Err(format!(
+            "{} must be {} bytes, got {}",
+            field,
+            len,
+            bytes.len()
+        ));
+    }
+    Ok(())
 }
 
 // Only for logs
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
 pub struct TransformedBlockResponse {
     pub public_key: TransformPublicKeyCollection,
-    pub encrypted_temp_key: String,
-    pub encrypted_random_transform_temp_key: String,
+    pub encrypted_temp_key: HexBytes,
+    pub encrypted_random_transform_temp_key: HexBytes,
     pub random_transform_public_key: TransformPublicKeyCollection,
 }
 
@@ -23,35 +351,639 @@ pub struct TransformedBlockResponse {
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
 pub struct TransformedObject {
     pub ephemeral_public_key: TransformPublicKeyCollection,
-    pub encrypted_message: String,
-    pub auth_hash: String,
-    pub transform_blocks: TransformedBlockResponse,
-    pub public_signing_key: String,
-    pub ed25519_signature: String,
+    pub encrypted_message: HexBytes,
+    pub auth_hash: HexBytes,
+    // One block per re-encryption hop
+    pub transform_blocks: Vec<TransformedBlockResponse>,
+    pub public_signing_key: HexBytes,
+    pub ed25519_signature: HexBytes,
+    // Only set with `--include-freshness`. Advisory: not signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<HexBytes>,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
 pub struct TransformPublicKeyCollection {
-    pub public_key_x: String,
-    pub public_key_y: String,
+    pub public_key_x: HexBytes,
+    pub public_key_y: HexBytes,
+}
+
+impl From<&PublicKey> for TransformPublicKeyCollection {
+    fn from(public_key: &PublicKey) -> Self {
+        let (x, y) = public_key.bytes_x_y();
+        TransformPublicKeyCollection {
+            public_key_x: HexBytes::from(&x[..]),
+            public_key_y: HexBytes::from(&y[..]),
+        }
+    }
+}
+
+impl TryFrom<&TransformPublicKeyCollection> for PublicKey {
+    type Error = String;
+
+    fn try_from(collection: &TransformPublicKeyCollection) -> Result<Self, Self::Error> {
+        PublicKey::new_from_slice((&collection.public_key_x, &collection.public_key_y))
+            .map_err(|err| format!("invalid public key: {:?}", err))
+    }
+}
+
+/// The parts of a recrypt transform key, as written by the
+/// `transform-key` command and read back by `encrypt --transform-key-file`
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct TransformKeyCollection {
+    pub ephemeral_public_key: TransformPublicKeyCollection,
+    pub to_public_key: TransformPublicKeyCollection,
+    pub encrypted_temp_key: HexBytes,
+    pub hashed_temp_key: HexBytes,
+    pub public_signing_key: HexBytes,
+    pub signature: HexBytes,
+}
+
+impl TransformKeyCollection {
+    /// Protobuf encoding, used for `Payload::transform_key`. There is no
+    /// message for transform keys in `transform.proto`, so the key is a
+    /// `TransformObject` with one `TransformBlock`: the ephemeral and
+    /// target public keys go in `public_key` and
+    /// `random_transform_public_key`, the encrypted and hashed temp keys
+    /// in `encrypted_temp_key` and `encrypted_random_transform_temp_key`,
+    /// and the signing key and signature in the object's
+    /// `public_signing_key` and `ed25519_signature`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let public_key = |key: &TransformPublicKeyCollection| {
+            MessageField::some(PPK {
+                x: key.public_key_x.0.clone(),
+                y: key.public_key_y.0.clone(),
+                ..Default::default()
+            })
+        };
+        let block = TFB {
+            public_key: public_key(&self.ephemeral_public_key),
+            encrypted_temp_key: self.encrypted_temp_key.0.clone(),
+            random_transform_public_key: public_key(&self.to_public_key),
+            encrypted_random_transform_temp_key: self.hashed_temp_key.0.clone(),
+            ..Default::default()
+        };
+        let object = TFO {
+            transform_blocks: vec![block],
+            public_signing_key: self.public_signing_key.0.clone(),
+            ed25519_signature: self.signature.0.clone(),
+            ..Default::default()
+        };
+        // Writing only fails for messages over 2 GiB
+        object
+            .write_to_bytes()
+            .expect("transform key fits in a protobuf message")
+    }
+
+    /// Inverse of `to_bytes`. Only the message structure is checked here;
+    /// the parts are validated when converted to a recrypt `TransformKey`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let object = TFO::parse_from_bytes(bytes)
+            .map_err(|err| format!("transform_key is not a valid protobuf message: {}", err))?;
+        let block = match &object.transform_blocks[..] {
+            [block] => block,
+            blocks => {
+                return Err(format!(
+                    "transform_key must have 1 transform block, got {}",
+                    blocks.len()
+                ))
+            }
+        };
+        let public_key = |key: &MessageField<PPK>, part: &str| {
+            key.as_ref()
+                .map(TransformPublicKeyCollection::from)
+                .ok_or_else(|| format!("transform_key is missing the {}", part))
+        };
+
+        Ok(TransformKeyCollection {
+            ephemeral_public_key: public_key(&block.public_key, "ephemeral public key")?,
+            to_public_key: public_key(&block.random_transform_public_key, "target public key")?,
+            encrypted_temp_key: HexBytes::from(&block.encrypted_temp_key[..]),
+            hashed_temp_key: HexBytes::from(&block.encrypted_random_transform_temp_key[..]),
+            public_signing_key: HexBytes::from(&object.public_signing_key[..]),
+            signature: HexBytes::from(&object.ed25519_signature[..]),
+        })
+    }
+}
+
+impl From<&TransformKey> for TransformKeyCollection {
+    fn from(transform_key: &TransformKey) -> Self {
+        TransformKeyCollection {
+            ephemeral_public_key: transform_key.ephemeral_public_key().into(),
+            to_public_key: transform_key.to_public_key().into(),
+            encrypted_temp_key: HexBytes::from(&transform_key.encrypted_temp_key().bytes()[..]),
+            hashed_temp_key: HexBytes::from(&transform_key.hashed_temp_key().bytes()[..]),
+            public_signing_key: HexBytes::from(&transform_key.public_signing_key().bytes()[..]),
+            signature: HexBytes::from(&transform_key.signature().bytes()[..]),
+        }
+    }
+}
+
+impl TryFrom<&TransformKeyCollection> for TransformKey {
+    type Error = String;
+
+    fn try_from(collection: &TransformKeyCollection) -> Result<Self, Self::Error> {
+        let invalid =
+            |part: &str, err: RecryptErr| format!("transform_key has invalid {}: {:?}", part, err);
+        Ok(TransformKey::new(
+            PublicKey::new_from_slice((
+                &collection.ephemeral_public_key.public_key_x,
+                &collection.ephemeral_public_key.public_key_y,
+            ))
+            .map_err(|err| invalid("ephemeral public key", err))?,
+            PublicKey::new_from_slice((
+                &collection.to_public_key.public_key_x,
+                &collection.to_public_key.public_key_y,
+            ))
+            .map_err(|err| invalid("target public key", err))?,
+            EncryptedTempKey::new_from_slice(&collection.encrypted_temp_key)
+                .map_err(|err| invalid("encrypted temp key", err))?,
+            HashedValue::new_from_slice(&collection.hashed_temp_key)
+                .map_err(|err| invalid("hashed temp key", err))?,
+            PublicSigningKey::new_from_slice(&collection.public_signing_key)
+                .map_err(|err| invalid("public signing key", err))?,
+            Ed25519Signature::new_from_slice(&collection.signature)
+                .map_err(|err| invalid("signature", err))?,
+        ))
+    }
+}
+
+/// Hex of `bytes`, shortened to its first and last 8 characters
+fn short_hex(bytes: &[u8]) -> String {
+    let hex = hex::encode(bytes);
+    if hex.len() <= 16 {
+        return hex;
+    }
+    format!("{}...{}", &hex[..8], &hex[hex.len() - 8..])
+}
+
+impl fmt::Display for TransformPublicKeyCollection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "x {}, y {}",
+            short_hex(&self.public_key_x),
+            short_hex(&self.public_key_y)
+        )
+    }
+}
+
+impl fmt::Display for TransformedBlockResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "public_key:                          {}",
+            self.public_key
+        )?;
+        writeln!(
+            f,
+            "encrypted_temp_key:                  {}",
+            short_hex(&self.encrypted_temp_key)
+        )?;
+        writeln!(
+            f,
+            "encrypted_random_transform_temp_key: {}",
+            short_hex(&self.encrypted_random_transform_temp_key)
+        )?;
+        write!(
+            f,
+            "random_transform_public_key:         {}",
+            self.random_transform_public_key
+        )
+    }
+}
+
+impl fmt::Display for TransformedObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "ephemeral_public_key: {}", self.ephemeral_public_key)?;
+        writeln!(
+            f,
+            "encrypted_message:    {}",
+            short_hex(&self.encrypted_message)
+        )?;
+        writeln!(f, "auth_hash:            {}", short_hex(&self.auth_hash))?;
+        writeln!(f, "transform_blocks:")?;
+        for (hop, block) in self.transform_blocks.iter().enumerate() {
+            writeln!(f, "  [{}]", hop)?;
+            for line in block.to_string().lines() {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        writeln!(
+            f,
+            "public_signing_key:   {}",
+            short_hex(&self.public_signing_key)
+        )?;
+        write!(
+            f,
+            "ed25519_signature:    {}",
+            short_hex(&self.ed25519_signature)
+        )
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 pub struct EncryptedResponse {
-    pub sender_public_key: String,
-    pub encrypted_resource: String,
-    pub transformed: String,
+    pub sender_public_key: HexBytes,
+    pub encrypted_resource: HexBytes,
+    pub transformed: HexBytes,
     pub transformed_response: TransformedObject,
+    // `transformed` again, only set when `?encoding=` asks for other than hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transformed_object: Option<String>,
+    // Same as `TransformedObjectResponse::transform_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform_key: Option<HexBytes>,
+}
+
+impl EncryptedResponse {
+    /// Check an upload before it is stored. The transformed object is the
+    /// resource a later decrypt needs, so it must be present.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.transformed.is_empty() {
+            return Err("resource must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Byte field input that is either an array of bytes or a hex string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BytesOrHex {
+    Bytes(Vec<u8>),
+    Hex(HexBytes),
 }
 
+fn bytes_or_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    match BytesOrHex::deserialize(deserializer)? {
+        BytesOrHex::Bytes(bytes) => Ok(bytes),
+        BytesOrHex::Hex(hex) => Ok(hex.0),
+    }
+}
+
+fn secret_bytes_or_hex<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<SecretBytes, D::Error> {
+    bytes_or_hex(deserializer).map(SecretBytes)
+}
+
+/// Serialized with byte arrays; deserialized from byte arrays or hex strings
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
 pub struct Keys {
-    pub private_key: Vec<u8>,
+    #[serde(deserialize_with = "secret_bytes_or_hex")]
+    pub private_key: SecretBytes,
+    #[serde(deserialize_with = "bytes_or_hex")]
+    pub public_key_x: Vec<u8>,
+    #[serde(deserialize_with = "bytes_or_hex")]
+    pub public_key_y: Vec<u8>,
+}
+
+/// `Keys` with hex strings instead of byte arrays
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct KeysHex {
+    #[serde(with = "hex_bytes")]
+    pub private_key: SecretBytes,
+    #[serde(with = "hex_bytes")]
     pub public_key_x: Vec<u8>,
+    #[serde(with = "hex_bytes")]
     pub public_key_y: Vec<u8>,
 }
 
+impl From<Keys> for KeysHex {
+    fn from(keys: Keys) -> Self {
+        KeysHex {
+            private_key: keys.private_key,
+            public_key_x: keys.public_key_x,
+            public_key_y: keys.public_key_y,
+        }
+    }
+}
+
+impl From<KeysHex> for Keys {
+    fn from(keys: KeysHex) -> Self {
+        Keys {
+            private_key: keys.private_key,
+            public_key_x: keys.public_key_x,
+            public_key_y: keys.public_key_y,
+        }
+    }
+}
+
+/// An ed25519 key pair; the private key is zeroed on drop
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct Ed25519Keys {
+    pub private_key: HexBytes,
+    #[zeroize(skip)]
+    pub public_key: HexBytes,
+}
+
+/// Body of `/transform-key`. Byte fields are accepted as byte arrays or
+/// hex strings, as in `Payload`.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct TransformKeyPayload {
+    #[serde(deserialize_with = "secret_bytes_or_hex")]
+    pub initial_private_key: SecretBytes,
+    #[serde(deserialize_with = "bytes_or_hex")]
+    pub delegatee_public_key_x: Vec<u8>,
+    #[serde(deserialize_with = "bytes_or_hex")]
+    pub delegatee_public_key_y: Vec<u8>,
+}
+
+/// Transform key in the encoding `/fetch-content` accepts as
+/// `Payload::transform_key`
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct TransformKeyResponse {
+    pub transform_key: HexBytes,
+}
+
+/// Body of `/decrypt`. The transformed object is kept as a string so
+/// malformed hex is reported by the handler rather than the JSON guard.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct DecryptPayload {
+    #[serde(deserialize_with = "secret_bytes_or_hex")]
+    pub delegatee_private_key: SecretBytes,
+    pub transformed_object: String,
+}
+
+/// Body of `/resources/<id>/decrypt`. A `transform_key` re-encrypts the
+/// stored resource one more hop, to the holder of `delegatee_private_key`.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct ResourceDecryptPayload {
+    #[serde(deserialize_with = "secret_bytes_or_hex")]
+    pub delegatee_private_key: SecretBytes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform_key: Option<HexBytes>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct DecryptResponse {
+    pub plaintext: HexBytes,
+}
+
+/// Body of `/verify`, a hex transformed object from `/fetch-content`
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct VerifyPayload {
+    pub transformed_object: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct VerifyResponse {
+    pub valid: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct HealthResponse {
+    pub status: String,
+    pub version: String,
+    pub uptime_secs: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct UploadResponse {
+    pub id: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct SigningKeyResponse {
+    pub public_signing_key: HexBytes,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct CryptoDiagnostics {
+    pub plaintext_len: usize,
+    pub private_key_len: usize,
+    pub public_key_len: usize,
+    pub recrypt_version: String,
+    pub public_signing_key: HexBytes,
+}
+
+/// `TransformedObjectResponse` as sent to clients that accept
+/// `application/x-bincode`: raw bytes instead of hex strings, and
+/// no skipped fields since bincode is not self-describing
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct BinaryTransformedObjectResponse {
+    pub transformed_object: Vec<u8>,
+    pub timestamp: Option<u64>,
+    pub nonce: Option<Vec<u8>>,
+    pub transform_key: Option<Vec<u8>>,
+}
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
 pub struct TransformedObjectResponse {
+    // Hex unless `?encoding=base64` was requested
     pub transformed_object: String,
-}
\ No newline at end of file
+    // Only set with `--include-freshness`. Advisory: not signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<HexBytes>,
+    // Only set with `?include_transform_key=true` and `--allow-transform-key-disclosure`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform_key: Option<HexBytes>,
+}
+/// Output of `encrypt --plaintext-file`: one transformed object per
+/// plaintext chunk, in order. The last chunk is zero-padded, so only the
+/// first `original_len` bytes of the decrypted data are the file.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+pub struct ChunkedTransformResponse {
+    pub original_len: usize,
+    pub chunks: Vec<TransformedObjectResponse>,
+}
+
+/// Error body returned by the HTTP routes, sent with `status`
+#[derive(Debug, PartialEq, Eq, Serialize, Clone)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: Status,
+    pub error: String,
+}
+
+impl ApiError {
+    pub fn internal(error: String) -> Self {
+        ApiError {
+            status: Status::InternalServerError,
+            error,
+        }
+    }
+
+    pub fn service_unavailable(error: String) -> Self {
+        ApiError {
+            status: Status::ServiceUnavailable,
+            error,
+        }
+    }
+
+    pub fn bad_request(error: String) -> Self {
+        ApiError {
+            status: Status::BadRequest,
+            error,
+        }
+    }
+
+    pub fn payload_too_large(error: String) -> Self {
+        ApiError {
+            status: Status::PayloadTooLarge,
+            error,
+        }
+    }
+
+    pub fn unauthorized(error: String) -> Self {
+        ApiError {
+            status: Status::Unauthorized,
+            error,
+        }
+    }
+
+    pub fn unsupported_media_type(error: String) -> Self {
+        ApiError {
+            status: Status::UnsupportedMediaType,
+            error,
+        }
+    }
+
+    pub fn not_found(error: String) -> Self {
+        ApiError {
+            status: Status::NotFound,
+            error,
+        }
+    }
+}
+
+impl<'r> Responder<'r> for ApiError {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        let status = self.status;
+        Response::build_from(Json(self).respond_to(req)?)
+            .status(status)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use recrypt::api::{CryptoOps, Ed25519Ops, KeyGenOps, Recrypt};
+
+    fn transform_key_collection() -> TransformKeyCollection {
+        let recrypt = Recrypt::new();
+        let signing_keypair = recrypt.generate_ed25519_key_pair();
+        let (owner_private_key, _) = recrypt.generate_key_pair().unwrap();
+        let (_, delegatee_public_key) = recrypt.generate_key_pair().unwrap();
+        let transform_key = recrypt
+            .generate_transform_key(&owner_private_key, &delegatee_public_key, &signing_keypair)
+            .unwrap();
+        TransformKeyCollection::from(&transform_key)
+    }
+
+    #[test]
+    fn transform_key_survives_bytes_round_trip() {
+        let collection = transform_key_collection();
+        let bytes = collection.to_bytes();
+        let object = TFO::parse_from_bytes(&bytes).unwrap();
+        assert_eq!(object.transform_blocks.len(), 1);
+        assert_eq!(object.ed25519_signature, collection.signature.0);
+
+        let parsed = TransformKeyCollection::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, collection);
+        assert!(TransformKey::try_from(&parsed).is_ok());
+    }
+
+    #[test]
+    fn truncated_transform_key_is_rejected() {
+        let bytes = transform_key_collection().to_bytes();
+        assert!(TransformKeyCollection::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn transform_key_without_a_block_is_rejected() {
+        let bytes = TFO::new().write_to_bytes().unwrap();
+        assert_eq!(
+            TransformKeyCollection::from_bytes(&bytes),
+            Err("transform_key must have 1 transform block, got 0".to_string())
+        );
+    }
+
+    #[test]
+    fn transform_key_payload_accepts_hex_and_byte_arrays() {
+        let from_hex: TransformKeyPayload = serde_json::from_str(
+            r#"{"initial_private_key": "0102", "delegatee_public_key_x": "0a0b",
+                "delegatee_public_key_y": "ff"}"#,
+        )
+        .unwrap();
+        let from_bytes: TransformKeyPayload = serde_json::from_str(
+            r#"{"initial_private_key": [1, 2], "delegatee_public_key_x": [10, 11],
+                "delegatee_public_key_y": [255]}"#,
+        )
+        .unwrap();
+        assert_eq!(from_hex, from_bytes);
+        assert_eq!(&*from_hex.initial_private_key, &[1, 2]);
+    }
+
+    fn zeroed_on_drop<T: ZeroizeOnDrop>() {}
+
+    #[test]
+    fn key_types_zero_their_secrets() {
+        zeroed_on_drop::<SecretBytes>();
+        zeroed_on_drop::<KeySeed>();
+        zeroed_on_drop::<Ed25519Keys>();
+
+        let mut keys = Ed25519Keys {
+            private_key: HexBytes(vec![0xa5; 32]),
+            public_key: HexBytes(vec![0x5a; 32]),
+        };
+        let private_key = keys.private_key.0.as_ptr();
+        keys.zeroize();
+
+        // `Vec::zeroize` clears the vector but keeps its allocation, so
+        // the old private key bytes can still be inspected
+        let old_private_key = unsafe { std::slice::from_raw_parts(private_key, 32) };
+        assert!(old_private_key.iter().all(|byte| *byte == 0));
+        assert!(keys.private_key.is_empty());
+        assert_eq!(keys.public_key.0, vec![0x5a; 32]);
+
+        let mut seed = KeySeed([7; 32]);
+        seed.zeroize();
+        assert_eq!(seed.0, [0; 32]);
+    }
+
+    #[test]
+    fn keys_round_trip_as_hex_strings() {
+        let json = r#"{"private_key":"01020304","public_key_x":"0a0b","public_key_y":"ff"}"#;
+        let hex_keys: KeysHex = serde_json::from_str(json).unwrap();
+        assert_eq!(hex_keys.private_key.0, vec![1, 2, 3, 4]);
+        assert_eq!(serde_json::to_string(&hex_keys).unwrap(), json);
+
+        let keys = Keys::from(hex_keys.clone());
+        assert_eq!(
+            serde_json::to_value(&keys).unwrap()["private_key"],
+            serde_json::json!([1, 2, 3, 4])
+        );
+        assert_eq!(KeysHex::from(keys), hex_keys);
+    }
+
+    #[test]
+    fn payload_round_trips_as_hex_strings() {
+        let json = serde_json::json!({
+            "initial_private_key": "01020304",
+            "initial_public_key_x": "05",
+            "initial_public_key_y": "06",
+            "delegatee_public_key_x": "07",
+            "delegatee_public_key_y": "08",
+            "resource": "",
+        });
+        let hex_payload: PayloadHex = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&hex_payload).unwrap(), json);
+
+        let payload = Payload::from(hex_payload.clone());
+        assert_eq!(payload.initial_private_key.0, vec![1, 2, 3, 4]);
+        assert_eq!(PayloadHex::from(payload), hex_payload);
+    }
+
+    #[test]
+    fn hex_bytes_fields_reject_invalid_hex() {
+        let json = r#"{"private_key":"0g","public_key_x":"","public_key_y":""}"#;
+        assert!(serde_json::from_str::<KeysHex>(json).is_err());
+    }
+}
@@ -176,11 +176,15 @@ pub struct TransformObject {
     // @@protoc_insertion_point(field:proxy_reencyption.proto.TransformObject.auth_hash)
     pub auth_hash: ::std::vec::Vec<u8>,
     // @@protoc_insertion_point(field:proxy_reencyption.proto.TransformObject.transform_blocks)
-    pub transform_blocks: ::protobuf::MessageField<TransformBlock>,
+    pub transform_blocks: ::std::vec::Vec<TransformBlock>,
     // @@protoc_insertion_point(field:proxy_reencyption.proto.TransformObject.public_signing_key)
     pub public_signing_key: ::std::vec::Vec<u8>,
     // @@protoc_insertion_point(field:proxy_reencyption.proto.TransformObject.ed25519_signature)
     pub ed25519_signature: ::std::vec::Vec<u8>,
+    // @@protoc_insertion_point(field:proxy_reencyption.proto.TransformObject.timestamp)
+    pub timestamp: u64,
+    // @@protoc_insertion_point(field:proxy_reencyption.proto.TransformObject.nonce)
+    pub nonce: ::std::vec::Vec<u8>,
     // special fields
     // @@protoc_insertion_point(special_field:proxy_reencyption.proto.TransformObject.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -198,7 +202,7 @@ impl TransformObject {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(6);
+        let mut fields = ::std::vec::Vec::with_capacity(8);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_message_field_accessor::<_, PublicKey>(
             "ephemeral_public_key",
@@ -215,7 +219,7 @@ impl TransformObject {
             |m: &TransformObject| { &m.auth_hash },
             |m: &mut TransformObject| { &mut m.auth_hash },
         ));
-        fields.push(::protobuf::reflect::rt::v2::make_message_field_accessor::<_, TransformBlock>(
+        fields.push(::protobuf::reflect::rt::v2::make_vec_simpler_accessor::<_, _>(
             "transform_blocks",
             |m: &TransformObject| { &m.transform_blocks },
             |m: &mut TransformObject| { &mut m.transform_blocks },
@@ -230,6 +234,16 @@ impl TransformObject {
             |m: &TransformObject| { &m.ed25519_signature },
             |m: &mut TransformObject| { &mut m.ed25519_signature },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "timestamp",
+            |m: &TransformObject| { &m.timestamp },
+            |m: &mut TransformObject| { &mut m.timestamp },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "nonce",
+            |m: &TransformObject| { &m.nonce },
+            |m: &mut TransformObject| { &mut m.nonce },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<TransformObject>(
             "TransformObject",
             fields,
@@ -258,7 +272,7 @@ impl ::protobuf::Message for TransformObject {
                     self.auth_hash = is.read_bytes()?;
                 },
                 34 => {
-                    ::protobuf::rt::read_singular_message_into_field(is, &mut self.transform_blocks)?;
+                    self.transform_blocks.push(is.read_message()?);
                 },
                 42 => {
                     self.public_signing_key = is.read_bytes()?;
@@ -266,6 +280,12 @@ impl ::protobuf::Message for TransformObject {
                 50 => {
                     self.ed25519_signature = is.read_bytes()?;
                 },
+                56 => {
+                    self.timestamp = is.read_uint64()?;
+                },
+                66 => {
+                    self.nonce = is.read_bytes()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -288,16 +308,22 @@ impl ::protobuf::Message for TransformObject {
         if !self.auth_hash.is_empty() {
             my_size += ::protobuf::rt::bytes_size(3, &self.auth_hash);
         }
-        if let Some(v) = self.transform_blocks.as_ref() {
-            let len = v.compute_size();
+        for value in &self.transform_blocks {
+            let len = value.compute_size();
             my_size += 1 + ::protobuf::rt::compute_raw_varint64_size(len) + len;
-        }
+        };
         if !self.public_signing_key.is_empty() {
             my_size += ::protobuf::rt::bytes_size(5, &self.public_signing_key);
         }
         if !self.ed25519_signature.is_empty() {
             my_size += ::protobuf::rt::bytes_size(6, &self.ed25519_signature);
         }
+        if self.timestamp != 0 {
+            my_size += ::protobuf::rt::uint64_size(7, self.timestamp);
+        }
+        if !self.nonce.is_empty() {
+            my_size += ::protobuf::rt::bytes_size(8, &self.nonce);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
@@ -313,15 +339,21 @@ impl ::protobuf::Message for TransformObject {
         if !self.auth_hash.is_empty() {
             os.write_bytes(3, &self.auth_hash)?;
         }
-        if let Some(v) = self.transform_blocks.as_ref() {
+        for v in &self.transform_blocks {
             ::protobuf::rt::write_message_field_with_cached_size(4, v, os)?;
-        }
+        };
         if !self.public_signing_key.is_empty() {
             os.write_bytes(5, &self.public_signing_key)?;
         }
         if !self.ed25519_signature.is_empty() {
             os.write_bytes(6, &self.ed25519_signature)?;
         }
+        if self.timestamp != 0 {
+            os.write_uint64(7, self.timestamp)?;
+        }
+        if !self.nonce.is_empty() {
+            os.write_bytes(8, &self.nonce)?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -345,6 +377,8 @@ impl ::protobuf::Message for TransformObject {
         self.transform_blocks.clear();
         self.public_signing_key.clear();
         self.ed25519_signature.clear();
+        self.timestamp = 0;
+        self.nonce.clear();
         self.special_fields.clear();
     }
 
@@ -353,9 +387,11 @@ impl ::protobuf::Message for TransformObject {
             ephemeral_public_key: ::protobuf::MessageField::none(),
             encrypted_message: ::std::vec::Vec::new(),
             auth_hash: ::std::vec::Vec::new(),
-            transform_blocks: ::protobuf::MessageField::none(),
+            transform_blocks: ::std::vec::Vec::new(),
             public_signing_key: ::std::vec::Vec::new(),
             ed25519_signature: ::std::vec::Vec::new(),
+            timestamp: 0,
+            nonce: ::std::vec::Vec::new(),
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -753,44 +789,46 @@ impl ::protobuf::reflect::ProtobufValue for EncryptedMessage {
 }
 
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x0ftransform.proto\x12\x0fproxy_reencyption.proto\"'\n\tPublicKey\x12\x0c\n\
-    \x01x\x18\x01\x20\x01(\x0cR\x01x\x12\x0c\n\x01y\x18\x02\x20\x01(\x0cR\
-    \x01y\"\xd0\x02\n\x0fTransformObject\x12L\n\x14ephemeral_public_key\x18\
-    \x01\x20\x01(\x0b2\x1a.proxy_reencyption.proto.PublicKeyR\x12ephemeralPublicKey\
-    \x12+\n\x11encrypted_message\x18\x02\x20\x01(\x0cR\x10encryptedMessage\
-    \x12\x1b\n\tauth_hash\x18\x03\x20\x01(\x0cR\x08authHash\x12J\n\x10transf\
-    orm_blocks\x18\x04\x20\x01(\x0b2\x1f.proxy_reencyption.proto.TransformBlockR\x0f\
-    transformBlocks\x12,\n\x12public_signing_key\x18\x05\x20\x01(\x0cR\x10pu\
-    blicSigningKey\x12+\n\x11ed25519_signature\x18\x06\x20\x01(\x0cR\x10ed25\
-    519Signature\"\xa2\x02\n\x0eTransformBlock\x129\n\npublic_key\x18\x01\
-    \x20\x01(\x0b2\x1a.proxy_reencyption.proto.PublicKeyR\tpublicKey\x12,\n\x12encry\
-    pted_temp_key\x18\x02\x20\x01(\x0cR\x10encryptedTempKey\x12Y\n\x1brandom\
-    _transform_public_key\x18\x03\x20\x01(\x0b2\x1a.proxy_reencyption.proto.PublicKe\
-    yR\x18randomTransformPublicKey\x12L\n#encrypted_random_transform_temp_ke\
-    y\x18\x04\x20\x01(\x0cR\x1fencryptedRandomTransformTempKey\"\x85\x02\n\
-    \x10EncryptedMessage\x12+\n\x11encrypted_message\x18\x01\x20\x01(\x0cR\
-    \x10encryptedMessage\x12L\n\x14ephemeral_public_key\x18\x02\x20\x01(\x0b\
-    2\x1a.proxy_reencyption.proto.PublicKeyR\x12ephemeralPublicKey\x12+\n\x11ed25519\
-    _signature\x18\x03\x20\x01(\x0cR\x10ed25519Signature\x12\x1b\n\tauth_has\
-    h\x18\x04\x20\x01(\x0cR\x08authHash\x12,\n\x12public_signing_key\x18\x05\
-    \x20\x01(\x0cR\x10publicSigningKeyJ\xa2\n\n\x06\x12\x04\0\0\x20\x01\n\
-    \x08\n\x01\x0c\x12\x03\0\0\x12\n\x08\n\x01\x02\x12\x03\x02\x08\x17\n\n\n\
-    \x02\x04\0\x12\x04\x04\0\x07\x01\n\n\n\x03\x04\0\x01\x12\x03\x04\x08\x11\
-    \n\x0b\n\x04\x04\0\x02\0\x12\x03\x05\x02\x0e\n\r\n\x05\x04\0\x02\0\x04\
-    \x12\x04\x05\x02\x04\x13\n\x0c\n\x05\x04\0\x02\0\x05\x12\x03\x05\x02\x07\
-    \n\x0c\n\x05\x04\0\x02\0\x01\x12\x03\x05\x08\t\n\x0c\n\x05\x04\0\x02\0\
-    \x03\x12\x03\x05\x0c\r\n\x0b\n\x04\x04\0\x02\x01\x12\x03\x06\x02\x0e\n\r\
-    \n\x05\x04\0\x02\x01\x04\x12\x04\x06\x02\x05\x0e\n\x0c\n\x05\x04\0\x02\
-    \x01\x05\x12\x03\x06\x02\x07\n\x0c\n\x05\x04\0\x02\x01\x01\x12\x03\x06\
-    \x08\t\n\x0c\n\x05\x04\0\x02\x01\x03\x12\x03\x06\x0c\r\n\n\n\x02\x04\x01\
-    \x12\x04\n\0\x11\x01\n\n\n\x03\x04\x01\x01\x12\x03\n\x08\x17\n\x0b\n\x04\
-    \x04\x01\x02\0\x12\x03\x0b\x02%\n\r\n\x05\x04\x01\x02\0\x04\x12\x04\x0b\
-    \x02\n\x19\n\x0c\n\x05\x04\x01\x02\0\x06\x12\x03\x0b\x02\x0b\n\x0c\n\x05\
-    \x04\x01\x02\0\x01\x12\x03\x0b\x0c\x20\n\x0c\n\x05\x04\x01\x02\0\x03\x12\
-    \x03\x0b#$\n\x0b\n\x04\x04\x01\x02\x01\x12\x03\x0c\x02\x1e\n\r\n\x05\x04\
-    \x01\x02\x01\x04\x12\x04\x0c\x02\x0b%\n\x0c\n\x05\x04\x01\x02\x01\x05\
-    \x12\x03\x0c\x02\x07\n\x0c\n\x05\x04\x01\x02\x01\x01\x12\x03\x0c\x08\x19\
-    \n\x0c\n\x05\x04\x01\x02\x01\x03\x12\x03\x0c\x1c\x1d\n\x0b\n\x04\x04\x01\
+    \n\x0ftransform.proto\x12\x17proxy_reencyption.proto\"'\n\tPublicKey\x12\
+    \x0c\n\x01x\x18\x01\x20\x01(\x0cR\x01x\x12\x0c\n\x01y\x18\x02\x20\x01(\
+    \x0cR\x01y\"\x94\x03\n\x0fTransformObject\x12T\n\x14ephemeral_public_key\
+    \x18\x01\x20\x01(\x0b2\".proxy_reencyption.proto.PublicKeyR\x12ephemeral\
+    PublicKey\x12+\n\x11encrypted_message\x18\x02\x20\x01(\x0cR\x10encrypted\
+    Message\x12\x1b\n\tauth_hash\x18\x03\x20\x01(\x0cR\x08authHash\x12R\n\
+    \x10transform_blocks\x18\x04\x20\x03(\x0b2'.proxy_reencyption.proto.Tran\
+    sformBlockR\x0ftransformBlocks\x12,\n\x12public_signing_key\x18\x05\x20\
+    \x01(\x0cR\x10publicSigningKey\x12+\n\x11ed25519_signature\x18\x06\x20\
+    \x01(\x0cR\x10ed25519Signature\x12\x1c\n\ttimestamp\x18\x07\x20\x01(\x04\
+    R\ttimestamp\x12\x14\n\x05nonce\x18\x08\x20\x01(\x0cR\x05nonce\"\xb2\x02\
+    \n\x0eTransformBlock\x12A\n\npublic_key\x18\x01\x20\x01(\x0b2\".proxy_re\
+    encyption.proto.PublicKeyR\tpublicKey\x12,\n\x12encrypted_temp_key\x18\
+    \x02\x20\x01(\x0cR\x10encryptedTempKey\x12a\n\x1brandom_transform_public\
+    _key\x18\x03\x20\x01(\x0b2\".proxy_reencyption.proto.PublicKeyR\x18rando\
+    mTransformPublicKey\x12L\n#encrypted_random_transform_temp_key\x18\x04\
+    \x20\x01(\x0cR\x1fencryptedRandomTransformTempKey\"\x8d\x02\n\x10Encrypt\
+    edMessage\x12+\n\x11encrypted_message\x18\x01\x20\x01(\x0cR\x10encrypted\
+    Message\x12T\n\x14ephemeral_public_key\x18\x02\x20\x01(\x0b2\".proxy_ree\
+    ncyption.proto.PublicKeyR\x12ephemeralPublicKey\x12+\n\x11ed25519_signat\
+    ure\x18\x03\x20\x01(\x0cR\x10ed25519Signature\x12\x1b\n\tauth_hash\x18\
+    \x04\x20\x01(\x0cR\x08authHash\x12,\n\x12public_signing_key\x18\x05\x20\
+    \x01(\x0cR\x10publicSigningKeyJ\xae\x0b\n\x06\x12\x04\0\0\"\x01\n\x08\n\
+    \x01\x0c\x12\x03\0\0\x12\n\x08\n\x01\x02\x12\x03\x02\x08\x17\n\n\n\x02\
+    \x04\0\x12\x04\x04\0\x07\x01\n\n\n\x03\x04\0\x01\x12\x03\x04\x08\x11\n\
+    \x0b\n\x04\x04\0\x02\0\x12\x03\x05\x02\x0e\n\r\n\x05\x04\0\x02\0\x04\x12\
+    \x04\x05\x02\x04\x13\n\x0c\n\x05\x04\0\x02\0\x05\x12\x03\x05\x02\x07\n\
+    \x0c\n\x05\x04\0\x02\0\x01\x12\x03\x05\x08\t\n\x0c\n\x05\x04\0\x02\0\x03\
+    \x12\x03\x05\x0c\r\n\x0b\n\x04\x04\0\x02\x01\x12\x03\x06\x02\x0e\n\r\n\
+    \x05\x04\0\x02\x01\x04\x12\x04\x06\x02\x05\x0e\n\x0c\n\x05\x04\0\x02\x01\
+    \x05\x12\x03\x06\x02\x07\n\x0c\n\x05\x04\0\x02\x01\x01\x12\x03\x06\x08\t\
+    \n\x0c\n\x05\x04\0\x02\x01\x03\x12\x03\x06\x0c\r\n\n\n\x02\x04\x01\x12\
+    \x04\n\0\x13\x01\n\n\n\x03\x04\x01\x01\x12\x03\n\x08\x17\n\x0b\n\x04\x04\
+    \x01\x02\0\x12\x03\x0b\x02%\n\r\n\x05\x04\x01\x02\0\x04\x12\x04\x0b\x02\
+    \n\x19\n\x0c\n\x05\x04\x01\x02\0\x06\x12\x03\x0b\x02\x0b\n\x0c\n\x05\x04\
+    \x01\x02\0\x01\x12\x03\x0b\x0c\x20\n\x0c\n\x05\x04\x01\x02\0\x03\x12\x03\
+    \x0b#$\n\x0b\n\x04\x04\x01\x02\x01\x12\x03\x0c\x02\x1e\n\r\n\x05\x04\x01\
+    \x02\x01\x04\x12\x04\x0c\x02\x0b%\n\x0c\n\x05\x04\x01\x02\x01\x05\x12\
+    \x03\x0c\x02\x07\n\x0c\n\x05\x04\x01\x02\x01\x01\x12\x03\x0c\x08\x19\n\
+    \x0c\n\x05\x04\x01\x02\x01\x03\x12\x03\x0c\x1c\x1d\n\x0b\n\x04\x04\x01\
     \x02\x02\x12\x03\r\x02\x16\n\r\n\x05\x04\x01\x02\x02\x04\x12\x04\r\x02\
     \x0c\x1e\n\x0c\n\x05\x04\x01\x02\x02\x05\x12\x03\r\x02\x07\n\x0c\n\x05\
     \x04\x01\x02\x02\x01\x12\x03\r\x08\x11\n\x0c\n\x05\x04\x01\x02\x02\x03\
@@ -804,42 +842,49 @@ static file_descriptor_proto_data: &'static [u8] = b"\
     \x03\x12\x03\x0f\x1c\x1d\n\x0b\n\x04\x04\x01\x02\x05\x12\x03\x10\x02\x1e\
     \n\r\n\x05\x04\x01\x02\x05\x04\x12\x04\x10\x02\x0f\x1e\n\x0c\n\x05\x04\
     \x01\x02\x05\x05\x12\x03\x10\x02\x07\n\x0c\n\x05\x04\x01\x02\x05\x01\x12\
-    \x03\x10\x08\x19\n\x0c\n\x05\x04\x01\x02\x05\x03\x12\x03\x10\x1c\x1d\n\n\
-    \n\x02\x04\x02\x12\x04\x13\0\x18\x01\n\n\n\x03\x04\x02\x01\x12\x03\x13\
-    \x08\x16\n\x0b\n\x04\x04\x02\x02\0\x12\x03\x14\x02\x1b\n\r\n\x05\x04\x02\
-    \x02\0\x04\x12\x04\x14\x02\x13\x18\n\x0c\n\x05\x04\x02\x02\0\x06\x12\x03\
-    \x14\x02\x0b\n\x0c\n\x05\x04\x02\x02\0\x01\x12\x03\x14\x0c\x16\n\x0c\n\
-    \x05\x04\x02\x02\0\x03\x12\x03\x14\x19\x1a\n\x0b\n\x04\x04\x02\x02\x01\
-    \x12\x03\x15\x02\x1f\n\r\n\x05\x04\x02\x02\x01\x04\x12\x04\x15\x02\x14\
-    \x1b\n\x0c\n\x05\x04\x02\x02\x01\x05\x12\x03\x15\x02\x07\n\x0c\n\x05\x04\
-    \x02\x02\x01\x01\x12\x03\x15\x08\x1a\n\x0c\n\x05\x04\x02\x02\x01\x03\x12\
-    \x03\x15\x1d\x1e\n\x0b\n\x04\x04\x02\x02\x02\x12\x03\x16\x02,\n\r\n\x05\
-    \x04\x02\x02\x02\x04\x12\x04\x16\x02\x15\x1f\n\x0c\n\x05\x04\x02\x02\x02\
-    \x06\x12\x03\x16\x02\x0b\n\x0c\n\x05\x04\x02\x02\x02\x01\x12\x03\x16\x0c\
-    '\n\x0c\n\x05\x04\x02\x02\x02\x03\x12\x03\x16*+\n\x0b\n\x04\x04\x02\x02\
-    \x03\x12\x03\x17\x020\n\r\n\x05\x04\x02\x02\x03\x04\x12\x04\x17\x02\x16,\
-    \n\x0c\n\x05\x04\x02\x02\x03\x05\x12\x03\x17\x02\x07\n\x0c\n\x05\x04\x02\
-    \x02\x03\x01\x12\x03\x17\x08+\n\x0c\n\x05\x04\x02\x02\x03\x03\x12\x03\
-    \x17./\n\n\n\x02\x04\x03\x12\x04\x1a\0\x20\x01\n\n\n\x03\x04\x03\x01\x12\
-    \x03\x1a\x08\x18\n\x0b\n\x04\x04\x03\x02\0\x12\x03\x1b\x02\x1e\n\r\n\x05\
-    \x04\x03\x02\0\x04\x12\x04\x1b\x02\x1a\x1a\n\x0c\n\x05\x04\x03\x02\0\x05\
-    \x12\x03\x1b\x02\x07\n\x0c\n\x05\x04\x03\x02\0\x01\x12\x03\x1b\x08\x19\n\
-    \x0c\n\x05\x04\x03\x02\0\x03\x12\x03\x1b\x1c\x1d\n\x0b\n\x04\x04\x03\x02\
-    \x01\x12\x03\x1c\x02%\n\r\n\x05\x04\x03\x02\x01\x04\x12\x04\x1c\x02\x1b\
-    \x1e\n\x0c\n\x05\x04\x03\x02\x01\x06\x12\x03\x1c\x02\x0b\n\x0c\n\x05\x04\
-    \x03\x02\x01\x01\x12\x03\x1c\x0c\x20\n\x0c\n\x05\x04\x03\x02\x01\x03\x12\
-    \x03\x1c#$\n\x0b\n\x04\x04\x03\x02\x02\x12\x03\x1d\x02\x1e\n\r\n\x05\x04\
-    \x03\x02\x02\x04\x12\x04\x1d\x02\x1c%\n\x0c\n\x05\x04\x03\x02\x02\x05\
-    \x12\x03\x1d\x02\x07\n\x0c\n\x05\x04\x03\x02\x02\x01\x12\x03\x1d\x08\x19\
-    \n\x0c\n\x05\x04\x03\x02\x02\x03\x12\x03\x1d\x1c\x1d\n\x0b\n\x04\x04\x03\
-    \x02\x03\x12\x03\x1e\x02\x16\n\r\n\x05\x04\x03\x02\x03\x04\x12\x04\x1e\
-    \x02\x1d\x1e\n\x0c\n\x05\x04\x03\x02\x03\x05\x12\x03\x1e\x02\x07\n\x0c\n\
-    \x05\x04\x03\x02\x03\x01\x12\x03\x1e\x08\x11\n\x0c\n\x05\x04\x03\x02\x03\
-    \x03\x12\x03\x1e\x14\x15\n\x0b\n\x04\x04\x03\x02\x04\x12\x03\x1f\x02\x1f\
-    \n\r\n\x05\x04\x03\x02\x04\x04\x12\x04\x1f\x02\x1e\x16\n\x0c\n\x05\x04\
-    \x03\x02\x04\x05\x12\x03\x1f\x02\x07\n\x0c\n\x05\x04\x03\x02\x04\x01\x12\
-    \x03\x1f\x08\x1a\n\x0c\n\x05\x04\x03\x02\x04\x03\x12\x03\x1f\x1d\x1eb\
-    \x06proto3\
+    \x03\x10\x08\x19\n\x0c\n\x05\x04\x01\x02\x05\x03\x12\x03\x10\x1c\x1d\n\
+    \x0b\n\x04\x04\x01\x02\x06\x12\x03\x11\x02\x17\n\r\n\x05\x04\x01\x02\x06\
+    \x04\x12\x04\x11\x02\x10\x1e\n\x0c\n\x05\x04\x01\x02\x06\x05\x12\x03\x11\
+    \x02\x08\n\x0c\n\x05\x04\x01\x02\x06\x01\x12\x03\x11\t\x12\n\x0c\n\x05\
+    \x04\x01\x02\x06\x03\x12\x03\x11\x15\x16\n\x0b\n\x04\x04\x01\x02\x07\x12\
+    \x03\x12\x02\x12\n\r\n\x05\x04\x01\x02\x07\x04\x12\x04\x12\x02\x11\x17\n\
+    \x0c\n\x05\x04\x01\x02\x07\x05\x12\x03\x12\x02\x07\n\x0c\n\x05\x04\x01\
+    \x02\x07\x01\x12\x03\x12\x08\r\n\x0c\n\x05\x04\x01\x02\x07\x03\x12\x03\
+    \x12\x10\x11\n\n\n\x02\x04\x02\x12\x04\x15\0\x1a\x01\n\n\n\x03\x04\x02\
+    \x01\x12\x03\x15\x08\x16\n\x0b\n\x04\x04\x02\x02\0\x12\x03\x16\x02\x1b\n\
+    \r\n\x05\x04\x02\x02\0\x04\x12\x04\x16\x02\x15\x18\n\x0c\n\x05\x04\x02\
+    \x02\0\x06\x12\x03\x16\x02\x0b\n\x0c\n\x05\x04\x02\x02\0\x01\x12\x03\x16\
+    \x0c\x16\n\x0c\n\x05\x04\x02\x02\0\x03\x12\x03\x16\x19\x1a\n\x0b\n\x04\
+    \x04\x02\x02\x01\x12\x03\x17\x02\x1f\n\r\n\x05\x04\x02\x02\x01\x04\x12\
+    \x04\x17\x02\x16\x1b\n\x0c\n\x05\x04\x02\x02\x01\x05\x12\x03\x17\x02\x07\
+    \n\x0c\n\x05\x04\x02\x02\x01\x01\x12\x03\x17\x08\x1a\n\x0c\n\x05\x04\x02\
+    \x02\x01\x03\x12\x03\x17\x1d\x1e\n\x0b\n\x04\x04\x02\x02\x02\x12\x03\x18\
+    \x02,\n\r\n\x05\x04\x02\x02\x02\x04\x12\x04\x18\x02\x17\x1f\n\x0c\n\x05\
+    \x04\x02\x02\x02\x06\x12\x03\x18\x02\x0b\n\x0c\n\x05\x04\x02\x02\x02\x01\
+    \x12\x03\x18\x0c'\n\x0c\n\x05\x04\x02\x02\x02\x03\x12\x03\x18*+\n\x0b\n\
+    \x04\x04\x02\x02\x03\x12\x03\x19\x020\n\r\n\x05\x04\x02\x02\x03\x04\x12\
+    \x04\x19\x02\x18,\n\x0c\n\x05\x04\x02\x02\x03\x05\x12\x03\x19\x02\x07\n\
+    \x0c\n\x05\x04\x02\x02\x03\x01\x12\x03\x19\x08+\n\x0c\n\x05\x04\x02\x02\
+    \x03\x03\x12\x03\x19./\n\n\n\x02\x04\x03\x12\x04\x1c\0\"\x01\n\n\n\x03\
+    \x04\x03\x01\x12\x03\x1c\x08\x18\n\x0b\n\x04\x04\x03\x02\0\x12\x03\x1d\
+    \x02\x1e\n\r\n\x05\x04\x03\x02\0\x04\x12\x04\x1d\x02\x1c\x1a\n\x0c\n\x05\
+    \x04\x03\x02\0\x05\x12\x03\x1d\x02\x07\n\x0c\n\x05\x04\x03\x02\0\x01\x12\
+    \x03\x1d\x08\x19\n\x0c\n\x05\x04\x03\x02\0\x03\x12\x03\x1d\x1c\x1d\n\x0b\
+    \n\x04\x04\x03\x02\x01\x12\x03\x1e\x02%\n\r\n\x05\x04\x03\x02\x01\x04\
+    \x12\x04\x1e\x02\x1d\x1e\n\x0c\n\x05\x04\x03\x02\x01\x06\x12\x03\x1e\x02\
+    \x0b\n\x0c\n\x05\x04\x03\x02\x01\x01\x12\x03\x1e\x0c\x20\n\x0c\n\x05\x04\
+    \x03\x02\x01\x03\x12\x03\x1e#$\n\x0b\n\x04\x04\x03\x02\x02\x12\x03\x1f\
+    \x02\x1e\n\r\n\x05\x04\x03\x02\x02\x04\x12\x04\x1f\x02\x1e%\n\x0c\n\x05\
+    \x04\x03\x02\x02\x05\x12\x03\x1f\x02\x07\n\x0c\n\x05\x04\x03\x02\x02\x01\
+    \x12\x03\x1f\x08\x19\n\x0c\n\x05\x04\x03\x02\x02\x03\x12\x03\x1f\x1c\x1d\
+    \n\x0b\n\x04\x04\x03\x02\x03\x12\x03\x20\x02\x16\n\r\n\x05\x04\x03\x02\
+    \x03\x04\x12\x04\x20\x02\x1f\x1e\n\x0c\n\x05\x04\x03\x02\x03\x05\x12\x03\
+    \x20\x02\x07\n\x0c\n\x05\x04\x03\x02\x03\x01\x12\x03\x20\x08\x11\n\x0c\n\
+    \x05\x04\x03\x02\x03\x03\x12\x03\x20\x14\x15\n\x0b\n\x04\x04\x03\x02\x04\
+    \x12\x03!\x02\x1f\n\r\n\x05\x04\x03\x02\x04\x04\x12\x04!\x02\x20\x16\n\
+    \x0c\n\x05\x04\x03\x02\x04\x05\x12\x03!\x02\x07\n\x0c\n\x05\x04\x03\x02\
+    \x04\x01\x12\x03!\x08\x1a\n\x0c\n\x05\x04\x03\x02\x04\x03\x12\x03!\x1d\
+    \x1eb\x06proto3\
 ";
 
 /// `FileDescriptorProto` object which was a source for this generated file
@@ -0,0 +1,116 @@
+use crate::proto::transform::TransformObject;
+use protobuf::Message;
+use std::path::Path;
+
+/// protobuf release the code in `proto/transform.rs` was generated for
+const GENERATED_PROTOBUF_VERSION: &str = "3.1.0";
+
+/// Fail unless the linked protobuf library is from the same minor
+/// release as the generated code. Cargo may pick a newer patch release;
+/// patch releases keep the generated code's API, so only the major and
+/// minor numbers are compared.
+pub fn check_version() -> Result<(), String> {
+    check_compatible(::protobuf::VERSION, GENERATED_PROTOBUF_VERSION)
+}
+
+fn check_compatible(linked: &str, generated: &str) -> Result<(), String> {
+    if major_minor(linked) != major_minor(generated) {
+        return Err(format!(
+            "protobuf {} is linked, but the generated code expects {}",
+            linked, generated
+        ));
+    }
+    Ok(())
+}
+
+/// `"3.1.0"` -> `"3.1"`
+fn major_minor(version: &str) -> &str {
+    match version.match_indices('.').nth(1) {
+        Some((end, _)) => &version[..end],
+        None => version,
+    }
+}
+
+/// Serialize a transform object to the hex form handed out by
+/// `/fetch-content`
+pub fn tfo_to_hex(obj: &TransformObject) -> Result<String, String> {
+    obj.write_to_bytes()
+        .map(hex::encode)
+        .map_err(|err| format!("failed to serialize transform object: {}", err))
+}
+
+/// Parse a transform object from the hex form produced by `tfo_to_hex`
+pub fn tfo_from_hex(s: &str) -> Result<TransformObject, String> {
+    let bytes = hex::decode(s).map_err(|err| format!("transform object is not hex: {:?}", err))?;
+    TransformObject::parse_from_bytes(&bytes)
+        .map_err(|err| format!("failed to parse transform object: {}", err))
+}
+
+/// Write the raw protobuf encoding of `obj` to `path`, for byte-for-byte
+/// comparison with other implementations
+pub fn write_proto_to_file(obj: &impl Message, path: &Path) -> Result<(), String> {
+    let bytes = obj
+        .write_to_bytes()
+        .map_err(|err| format!("failed to serialize protobuf: {}", err))?;
+    std::fs::write(path, bytes)
+        .map_err(|err| format!("Failed to write {}: {:?}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::transform::PublicKey;
+
+    fn sample_tfo() -> TransformObject {
+        let mut tfo = TransformObject::new();
+        tfo.ephemeral_public_key = Some(PublicKey {
+            x: vec![1; 32],
+            y: vec![2; 32],
+            ..Default::default()
+        })
+        .into();
+        tfo.encrypted_message = vec![3; 384];
+        tfo.auth_hash = vec![4; 32];
+        tfo.public_signing_key = vec![5; 32];
+        tfo.ed25519_signature = vec![6; 64];
+        tfo
+    }
+
+    #[test]
+    fn patch_releases_are_compatible() {
+        assert!(check_compatible("3.1.0", "3.1.0").is_ok());
+        assert!(check_compatible("3.1.4", "3.1.0").is_ok());
+    }
+
+    #[test]
+    fn other_minor_releases_are_rejected() {
+        assert!(check_compatible("3.2.0", "3.1.0").is_err());
+        assert!(check_compatible("4.1.0", "3.1.0").is_err());
+    }
+
+    #[test]
+    fn transform_object_survives_hex_round_trip() {
+        let tfo = sample_tfo();
+        let hex = tfo_to_hex(&tfo).unwrap();
+        assert!(hex.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        assert_eq!(tfo_from_hex(&hex).unwrap(), tfo);
+    }
+
+    #[test]
+    fn invalid_hex_is_rejected() {
+        assert!(tfo_from_hex("not hex").is_err());
+        assert!(tfo_from_hex("ff").is_err());
+    }
+
+    #[test]
+    fn proto_file_parses_back_to_the_same_object() {
+        let tfo = sample_tfo();
+        let path = std::env::temp_dir().join(format!("tfo-{}.bin", std::process::id()));
+        write_proto_to_file(&tfo, &path).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bytes, tfo.write_to_bytes().unwrap());
+        assert_eq!(TransformObject::parse_from_bytes(&bytes).unwrap(), tfo);
+    }
+}
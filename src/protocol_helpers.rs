@@ -1,35 +1,93 @@
-use byteorder::{ByteOrder, LittleEndian};
-use nix::errno::Errno::EINTR;
+use crate::error::AppError;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use nix::errno::Errno::{EAGAIN, EINTR};
+use nix::sys::socket::sockopt::ReceiveTimeout;
 use nix::sys::socket::MsgFlags;
-use nix::sys::socket::{recv, send};
+use nix::sys::socket::{recv, send, setsockopt};
+use nix::sys::time::{TimeVal, TimeValLike};
 use std::convert::TryInto;
 use std::mem::size_of;
 use std::os::unix::io::RawFd;
+use std::time::Duration;
 
-pub fn send_u64(fd: RawFd, val: u64) -> Result<(), String> {
+pub mod async_io;
+
+pub fn send_u64(fd: RawFd, val: u64) -> Result<(), AppError> {
     let mut buf = [0u8; size_of::<u64>()];
     LittleEndian::write_u64(&mut buf, val);
-    send_loop(fd, &mut buf, size_of::<u64>().try_into().unwrap())?;
+    send_loop(fd, &buf, size_of::<u64>().try_into().unwrap())?;
     Ok(())
 }
 
-pub fn recv_u64(fd: RawFd) -> Result<u64, String> {
+pub fn recv_u64(fd: RawFd) -> Result<u64, AppError> {
     let mut buf = [0u8; size_of::<u64>()];
     recv_loop(fd, &mut buf, size_of::<u64>().try_into().unwrap())?;
     let val = LittleEndian::read_u64(&buf);
     Ok(val)
 }
 
+/// `send_u64` in network byte order, for peers such as Java clients
+/// that read big-endian length prefixes
+pub fn send_u64_be(fd: RawFd, val: u64) -> Result<(), AppError> {
+    let mut buf = [0u8; size_of::<u64>()];
+    BigEndian::write_u64(&mut buf, val);
+    send_loop(fd, &buf, size_of::<u64>().try_into().unwrap())?;
+    Ok(())
+}
+
+/// `recv_u64` in network byte order
+pub fn recv_u64_be(fd: RawFd) -> Result<u64, AppError> {
+    let mut buf = [0u8; size_of::<u64>()];
+    recv_loop(fd, &mut buf, size_of::<u64>().try_into().unwrap())?;
+    let val = BigEndian::read_u64(&buf);
+    Ok(val)
+}
+
+/// Send `buf` as a length-prefixed message. The payload goes through
+/// `send_loop`, so large messages survive partial writes.
+pub fn send_message(fd: RawFd, buf: &[u8]) -> Result<(), AppError> {
+    let len: u64 = buf
+        .len()
+        .try_into()
+        .map_err(|err| AppError::Protocol(format!("{:?}", err)))?;
+    send_u64(fd, len)?;
+    send_loop(fd, buf, len)
+}
+
+/// Receive one message written by `send_message`. The length prefix is
+/// checked against `max_len` before anything is allocated.
+pub fn recv_message(fd: RawFd, max_len: usize) -> Result<Vec<u8>, AppError> {
+    let len = recv_u64(fd)?;
+    let len_usize: usize = len
+        .try_into()
+        .map_err(|err| AppError::Protocol(format!("{:?}", err)))?;
+    if len_usize > max_len {
+        return Err(AppError::Protocol(format!(
+            "Message of {} bytes exceeds the {} byte limit",
+            len, max_len
+        )));
+    }
+
+    let mut buf = vec![0u8; len_usize];
+    recv_loop(fd, &mut buf, len)?;
+    Ok(buf)
+}
+
 /// Send `len` bytes from `buf` to a connection-oriented socket
-pub fn send_loop(fd: RawFd, buf: &[u8], len: u64) -> Result<(), String> {
-    let len: usize = len.try_into().map_err(|err| format!("{:?}", err))?;
+pub fn send_loop(fd: RawFd, buf: &[u8], len: u64) -> Result<(), AppError> {
+    let len: usize = len
+        .try_into()
+        .map_err(|err| AppError::Protocol(format!("{:?}", err)))?;
+    if len > buf.len() {
+        return Err(AppError::Protocol("len exceeds buffer length".to_string()));
+    }
     let mut send_bytes = 0;
 
     while send_bytes < len {
         let size = match send(fd, &buf[send_bytes..len], MsgFlags::empty()) {
             Ok(size) => size,
             Err(nix::Error::Sys(EINTR)) => 0,
-            Err(err) => return Err(format!("{:?}", err)),
+            Err(err) => return Err(err.into()),
         };
         send_bytes += size;
     }
@@ -37,19 +95,296 @@ pub fn send_loop(fd: RawFd, buf: &[u8], len: u64) -> Result<(), String> {
     Ok(())
 }
 
+/// Make blocking receives on `fd` give up after `timeout`, which
+/// `recv_loop` reports as "recv timed out". A zero timeout means no
+/// timeout, as for SO_RCVTIMEO itself.
+pub fn set_recv_timeout(fd: RawFd, timeout: Duration) -> Result<(), AppError> {
+    let timeval = TimeVal::microseconds(
+        timeout
+            .as_micros()
+            .try_into()
+            .map_err(|err| AppError::Protocol(format!("{:?}", err)))?,
+    );
+    setsockopt(fd, ReceiveTimeout, &timeval)
+        .map_err(|err| AppError::Socket(format!("Failed to set receive timeout: {:?}", err)))
+}
+
 /// Receive `len` bytes from a connection-orriented socket
-pub fn recv_loop(fd: RawFd, buf: &mut [u8], len: u64) -> Result<(), String> {
-    let len: usize = len.try_into().map_err(|err| format!("{:?}", err))?;
+pub fn recv_loop(fd: RawFd, buf: &mut [u8], len: u64) -> Result<(), AppError> {
+    let len: usize = len
+        .try_into()
+        .map_err(|err| AppError::Protocol(format!("{:?}", err)))?;
     let mut recv_bytes = 0;
 
     while recv_bytes < len {
         let size = match recv(fd, &mut buf[recv_bytes..len], MsgFlags::empty()) {
+            // The peer is gone; retrying would spin forever
+            Ok(0) => {
+                return Err(AppError::Socket(
+                    "connection closed before len bytes received".to_string(),
+                ))
+            }
             Ok(size) => size,
             Err(nix::Error::Sys(EINTR)) => 0,
-            Err(err) => return Err(format!("{:?}", err)),
+            // SO_RCVTIMEO expired, see `set_recv_timeout`
+            Err(nix::Error::Sys(EAGAIN)) => {
+                return Err(AppError::Socket("recv timed out".to_string()))
+            }
+            Err(err) => return Err(err.into()),
         };
         recv_bytes += size;
     }
 
     Ok(())
 }
+
+/// Receive a length-prefixed message that must be exactly `expected_len`
+/// bytes long and must not be followed by any further data. Used at the
+/// handshake boundary so a misframed peer is reported immediately
+/// instead of desynchronizing every read that follows.
+pub fn expect_exact(fd: RawFd, expected_len: u64) -> Result<Vec<u8>, AppError> {
+    let len = recv_u64(fd)?;
+    if len != expected_len {
+        return Err(AppError::Protocol(format!(
+            "Protocol desync: expected a {} byte message, got length prefix {}",
+            expected_len, len
+        )));
+    }
+
+    let mut buf = vec![
+        0u8;
+        len.try_into()
+            .map_err(|err| AppError::Protocol(format!("{:?}", err)))?
+    ];
+    recv_loop(fd, &mut buf, len)?;
+
+    // Peek without blocking: anything already queued behind the message
+    // means the peer framed it differently than we did
+    let mut trailing = [0u8; 1];
+    match recv(
+        fd,
+        &mut trailing,
+        MsgFlags::MSG_PEEK | MsgFlags::MSG_DONTWAIT,
+    ) {
+        Ok(0) | Err(nix::Error::Sys(EAGAIN)) => Ok(buf),
+        Ok(_) => Err(AppError::Protocol(
+            "Protocol desync: unexpected trailing data after message".to_string(),
+        )),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Reads a stream of length-prefixed messages, as written by `send_message`
+pub struct MessageReader {
+    fd: RawFd,
+    max_len: usize,
+}
+
+impl MessageReader {
+    /// Messages longer than `max_len` are rejected, as by `recv_message`
+    pub fn new(fd: RawFd, max_len: usize) -> Self {
+        MessageReader { fd, max_len }
+    }
+
+    /// Receive the next message, or `None` if the peer closed the
+    /// connection cleanly between messages
+    pub fn next_message(&mut self) -> Result<Option<Vec<u8>>, AppError> {
+        let mut len_buf = [0u8; size_of::<u64>()];
+        match recv_until_eof(self.fd, &mut len_buf)? {
+            0 => return Ok(None),
+            n if n < len_buf.len() => {
+                return Err(AppError::Protocol(
+                    "Connection closed inside a length prefix".to_string(),
+                ))
+            }
+            _ => {}
+        }
+
+        let len = LittleEndian::read_u64(&len_buf);
+        let len_usize: usize = len
+            .try_into()
+            .map_err(|err| AppError::Protocol(format!("{:?}", err)))?;
+        if len_usize > self.max_len {
+            return Err(AppError::Protocol(format!(
+                "Message of {} bytes exceeds the {} byte limit",
+                len, self.max_len
+            )));
+        }
+
+        let mut buf = vec![0u8; len_usize];
+        let received = recv_until_eof(self.fd, &mut buf)?;
+        if received < buf.len() {
+            return Err(AppError::Protocol(format!(
+                "Connection closed after {} of {} message bytes",
+                received, len
+            )));
+        }
+
+        Ok(Some(buf))
+    }
+}
+
+/// Fill `buf` from a connection-oriented socket, stopping early if the
+/// peer closes it. Returns the number of bytes received.
+fn recv_until_eof(fd: RawFd, buf: &mut [u8]) -> Result<usize, AppError> {
+    let mut recv_bytes = 0;
+
+    while recv_bytes < buf.len() {
+        let size = match recv(fd, &mut buf[recv_bytes..], MsgFlags::empty()) {
+            Ok(0) => break,
+            Ok(size) => size,
+            Err(nix::Error::Sys(EINTR)) => 0,
+            Err(err) => return Err(err.into()),
+        };
+        recv_bytes += size;
+    }
+
+    Ok(recv_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::sys::socket::{socketpair, AddressFamily, SockFlag, SockType};
+    use nix::unistd::close;
+
+    fn unix_socketpair() -> (RawFd, RawFd) {
+        socketpair(
+            AddressFamily::Unix,
+            SockType::Stream,
+            None,
+            SockFlag::empty(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn message_reader_rejects_oversized_length_before_allocating() {
+        let (local, peer) = unix_socketpair();
+        send_u64(peer, u64::MAX).unwrap();
+
+        let result = MessageReader::new(local, 1024).next_message();
+
+        close(local).unwrap();
+        close(peer).unwrap();
+        match result {
+            Err(AppError::Protocol(msg)) => assert!(msg.contains("exceeds the 1024 byte limit")),
+            other => panic!("expected a protocol error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn message_reader_returns_messages_until_eof() {
+        let (local, peer) = unix_socketpair();
+        for msg in [&b"one"[..], b"", b"three"] {
+            send_message(peer, msg).unwrap();
+        }
+        close(peer).unwrap();
+
+        let mut reader = MessageReader::new(local, 16);
+        assert_eq!(reader.next_message().unwrap(), Some(b"one".to_vec()));
+        assert_eq!(reader.next_message().unwrap(), Some(Vec::new()));
+        assert_eq!(reader.next_message().unwrap(), Some(b"three".to_vec()));
+        assert_eq!(reader.next_message().unwrap(), None);
+        close(local).unwrap();
+    }
+
+    #[test]
+    fn recv_times_out_when_peer_sends_nothing() {
+        let (local, peer) = unix_socketpair();
+        set_recv_timeout(local, Duration::from_millis(200)).unwrap();
+
+        let mut buf = [0u8; 8];
+        let result = recv_loop(local, &mut buf, 8);
+
+        close(local).unwrap();
+        close(peer).unwrap();
+        assert_eq!(result, Err(AppError::Socket("recv timed out".to_string())));
+    }
+
+    #[test]
+    fn send_loop_rejects_len_beyond_the_buffer() {
+        let (local, peer) = unix_socketpair();
+        let result = send_loop(local, &[0u8; 8], 16);
+
+        close(local).unwrap();
+        close(peer).unwrap();
+        assert_eq!(
+            result,
+            Err(AppError::Protocol("len exceeds buffer length".to_string()))
+        );
+    }
+
+    #[test]
+    fn recv_loop_reports_a_closed_peer() {
+        let (local, peer) = unix_socketpair();
+        send_loop(peer, &[1, 2, 3], 3).unwrap();
+        close(peer).unwrap();
+
+        let mut buf = [0u8; 8];
+        let result = recv_loop(local, &mut buf, 8);
+
+        close(local).unwrap();
+        assert_eq!(
+            result,
+            Err(AppError::Socket(
+                "connection closed before len bytes received".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn message_round_trips_4_kb() {
+        let (local, peer) = unix_socketpair();
+        let data: Vec<u8> = (0..4096).map(|i| (i % 251) as u8).collect();
+        send_message(peer, &data).unwrap();
+
+        let received = recv_message(local, data.len());
+
+        close(local).unwrap();
+        close(peer).unwrap();
+        assert_eq!(received, Ok(data));
+    }
+
+    #[test]
+    fn recv_message_rejects_messages_over_max_len() {
+        let (local, peer) = unix_socketpair();
+        send_message(peer, &[0u8; 64]).unwrap();
+
+        let result = recv_message(local, 32);
+
+        close(local).unwrap();
+        close(peer).unwrap();
+        assert_eq!(
+            result,
+            Err(AppError::Protocol(
+                "Message of 64 bytes exceeds the 32 byte limit".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn big_endian_u64_round_trips() {
+        let (local, peer) = unix_socketpair();
+        send_u64_be(peer, 0x0102_0304_0506_0708).unwrap();
+
+        let received = recv_u64_be(local);
+
+        close(local).unwrap();
+        close(peer).unwrap();
+        assert_eq!(received, Ok(0x0102_0304_0506_0708));
+    }
+
+    #[test]
+    fn big_endian_u64_is_sent_most_significant_byte_first() {
+        let (local, peer) = unix_socketpair();
+        send_u64_be(peer, 0x0102_0304_0506_0708).unwrap();
+
+        let mut wire = [0u8; 8];
+        recv_loop(local, &mut wire, 8).unwrap();
+
+        close(local).unwrap();
+        close(peer).unwrap();
+        assert_eq!(wire, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
@@ -0,0 +1,63 @@
+//! Non-blocking counterparts of the socket helpers, for use on a Tokio
+//! runtime. They use the same little-endian framing, so either side of
+//! a connection can be blocking or async.
+
+use crate::error::AppError;
+use byteorder::{ByteOrder, LittleEndian};
+use std::convert::TryInto;
+use std::io::ErrorKind;
+use std::mem::size_of;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub async fn send_u64<W: AsyncWrite + Unpin>(stream: &mut W, val: u64) -> Result<(), AppError> {
+    let mut buf = [0u8; size_of::<u64>()];
+    LittleEndian::write_u64(&mut buf, val);
+    send_loop(stream, &buf, size_of::<u64>().try_into().unwrap()).await
+}
+
+pub async fn recv_u64<R: AsyncRead + Unpin>(stream: &mut R) -> Result<u64, AppError> {
+    let mut buf = [0u8; size_of::<u64>()];
+    recv_loop(stream, &mut buf, size_of::<u64>().try_into().unwrap()).await?;
+    Ok(LittleEndian::read_u64(&buf))
+}
+
+/// Send `len` bytes from `buf`
+pub async fn send_loop<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    buf: &[u8],
+    len: u64,
+) -> Result<(), AppError> {
+    let len: usize = len
+        .try_into()
+        .map_err(|err| AppError::Protocol(format!("{:?}", err)))?;
+    if len > buf.len() {
+        return Err(AppError::Protocol("len exceeds buffer length".to_string()));
+    }
+
+    stream
+        .write_all(&buf[..len])
+        .await
+        .map_err(|err| AppError::Socket(format!("{:?}", err)))
+}
+
+/// Receive `len` bytes into `buf`
+pub async fn recv_loop<R: AsyncRead + Unpin>(
+    stream: &mut R,
+    buf: &mut [u8],
+    len: u64,
+) -> Result<(), AppError> {
+    let len: usize = len
+        .try_into()
+        .map_err(|err| AppError::Protocol(format!("{:?}", err)))?;
+    if len > buf.len() {
+        return Err(AppError::Protocol("len exceeds buffer length".to_string()));
+    }
+
+    match stream.read_exact(&mut buf[..len]).await {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => Err(AppError::Socket(
+            "connection closed before len bytes received".to_string(),
+        )),
+        Err(err) => Err(AppError::Socket(format!("{:?}", err))),
+    }
+}
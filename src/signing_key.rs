@@ -0,0 +1,107 @@
+use crate::command_parser::{FetchSigningKeyArgs, VerifySigningKeyArgs};
+use crate::models::SigningKeyResponse;
+use crate::proto_helpers::tfo_from_hex;
+use crate::utils::blocking_http_client;
+use log::warn;
+use std::fs;
+use std::io::ErrorKind;
+
+/// Download the server's public signing key from `args.url` and cache it
+/// in `args.output`, warning when it differs from the key cached before
+pub fn fetch_signing_key(args: FetchSigningKeyArgs) -> Result<(), String> {
+    // `/signing-key` is not behind `--api-key`
+    let fetched: SigningKeyResponse = blocking_http_client(None)?
+        .get(&args.url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json())
+        .map_err(|err| format!("Failed to fetch signing key from {}: {}", args.url, err))?;
+
+    cache_signing_key(&args.output, &fetched)?;
+    println!(
+        "Signing key {} written to {}",
+        hex::encode(&*fetched.public_signing_key),
+        args.output
+    );
+
+    Ok(())
+}
+
+/// Check offline that a transformed object was signed with the cached key
+pub fn verify_signing_key(args: VerifySigningKeyArgs) -> Result<(), String> {
+    let cached = read_cached_key(&args.key_file)?
+        .ok_or_else(|| format!("No cached signing key at {}", args.key_file))?;
+
+    let tfo = tfo_from_hex(&args.transformed_object)?;
+
+    if tfo.public_signing_key != *cached.public_signing_key {
+        return Err(format!(
+            "Transform object was signed with {}, cached key is {}",
+            hex::encode(&tfo.public_signing_key),
+            hex::encode(&*cached.public_signing_key)
+        ));
+    }
+
+    println!("Transform object was signed with the cached key");
+    Ok(())
+}
+
+/// Write `fetched` to `path`, warning when it replaces a different key
+fn cache_signing_key(path: &str, fetched: &SigningKeyResponse) -> Result<(), String> {
+    if let Some(cached) = read_cached_key(path)? {
+        if cached != *fetched {
+            warn!(
+                "Server signing key changed from {} to {}",
+                hex::encode(&*cached.public_signing_key),
+                hex::encode(&*fetched.public_signing_key)
+            );
+        }
+    }
+    write_cached_key(path, fetched)
+}
+
+fn read_cached_key(path: &str) -> Result<Option<SigningKeyResponse>, String> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("Failed to read {}: {:?}", path, err)),
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|err| format!("Failed to parse {}: {:?}", path, err))
+}
+
+fn write_cached_key(path: &str, key: &SigningKeyResponse) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(key)
+        .map_err(|err| format!("Failed to serialize signing key: {:?}", err))?;
+    fs::write(path, json).map_err(|err| format!("Failed to write {}: {:?}", path, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HexBytes;
+
+    #[test]
+    fn cached_key_reads_back_identically() {
+        let path = std::env::temp_dir().join(format!("signing-key-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(read_cached_key(path).unwrap(), None);
+
+        let first = SigningKeyResponse {
+            public_signing_key: HexBytes(vec![1; 32]),
+        };
+        cache_signing_key(path, &first).unwrap();
+        assert_eq!(read_cached_key(path).unwrap(), Some(first));
+
+        // A rotated key replaces the cached one
+        let rotated = SigningKeyResponse {
+            public_signing_key: HexBytes(vec![2; 32]),
+        };
+        cache_signing_key(path, &rotated).unwrap();
+        assert_eq!(read_cached_key(path).unwrap(), Some(rotated));
+
+        fs::remove_file(path).unwrap();
+    }
+}
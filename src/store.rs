@@ -0,0 +1,343 @@
+use crate::command_parser::{StoreBackend, StoreEviction};
+use crate::models::ApiError;
+use rocket::http::Status;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreError {
+    /// The store already holds `--max-resources` entries
+    Full,
+    InvalidId(String),
+    Io(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StoreError::Full => write!(f, "resource store is full"),
+            StoreError::InvalidId(id) => write!(f, "invalid resource id {:?}", id),
+            StoreError::Io(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(err: StoreError) -> Self {
+        let status = match err {
+            StoreError::Full => Status::InsufficientStorage,
+            StoreError::InvalidId(_) => Status::BadRequest,
+            StoreError::Io(_) => Status::InternalServerError,
+        };
+        ApiError {
+            status,
+            error: err.to_string(),
+        }
+    }
+}
+
+/// Storage for uploaded resources, keyed by resource id
+pub trait ResourceStore: Send + Sync {
+    fn insert(&self, id: &str, resource: &[u8]) -> Result<(), StoreError>;
+    fn get(&self, id: &str) -> Result<Option<Vec<u8>>, StoreError>;
+    /// Returns whether a resource was removed
+    fn delete(&self, id: &str) -> Result<bool, StoreError>;
+}
+
+/// Open the store selected by `--store-backend`, holding at most
+/// `max_resources` entries. Only the memory store can evict; the
+/// parser rejects `--store-eviction lru` with any other backend.
+pub fn open_store(
+    backend: &StoreBackend,
+    max_resources: Option<usize>,
+    eviction: StoreEviction,
+) -> Result<Box<dyn ResourceStore>, String> {
+    match backend {
+        StoreBackend::Memory => Ok(Box::new(MemoryStore::new(max_resources, eviction))),
+        StoreBackend::Fs(dir) => Ok(Box::new(FsStore::open(dir.into(), max_resources)?)),
+    }
+}
+
+#[derive(Default)]
+struct MemoryEntries {
+    // Resource and the tick it was last inserted or read at
+    resources: HashMap<String, (Vec<u8>, u64)>,
+    tick: u64,
+}
+
+impl MemoryEntries {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+/// Keeps resources for the lifetime of the process only
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: Mutex<MemoryEntries>,
+    max_resources: Option<usize>,
+    eviction: StoreEviction,
+}
+
+impl MemoryStore {
+    pub fn new(max_resources: Option<usize>, eviction: StoreEviction) -> Self {
+        MemoryStore {
+            entries: Mutex::new(MemoryEntries::default()),
+            max_resources,
+            eviction,
+        }
+    }
+}
+
+impl ResourceStore for MemoryStore {
+    /// At capacity, either fails with `StoreError::Full` or drops the
+    /// least recently used resource, depending on `eviction`. Replacing
+    /// an existing id is always allowed.
+    fn insert(&self, id: &str, resource: &[u8]) -> Result<(), StoreError> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(max) = self.max_resources {
+            if entries.resources.len() >= max && !entries.resources.contains_key(id) {
+                match self.eviction {
+                    StoreEviction::Reject => return Err(StoreError::Full),
+                    StoreEviction::Lru => {
+                        let oldest = entries
+                            .resources
+                            .iter()
+                            .min_by_key(|(_, (_, used))| *used)
+                            .map(|(id, _)| id.clone());
+                        if let Some(oldest) = oldest {
+                            entries.resources.remove(&oldest);
+                        }
+                    }
+                }
+            }
+        }
+        let tick = entries.next_tick();
+        entries
+            .resources
+            .insert(id.to_string(), (resource.to_vec(), tick));
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        let mut entries = self.entries.lock().unwrap();
+        let tick = entries.next_tick();
+        Ok(entries.resources.get_mut(id).map(|(resource, used)| {
+            *used = tick;
+            resource.clone()
+        }))
+    }
+
+    fn delete(&self, id: &str) -> Result<bool, StoreError> {
+        Ok(self.entries.lock().unwrap().resources.remove(id).is_some())
+    }
+}
+
+/// Stores each resource as a file named after its id under `dir`,
+/// so resources survive restarts
+pub struct FsStore {
+    dir: PathBuf,
+    max_resources: Option<usize>,
+    // Makes the capacity check and the write one step
+    inserting: Mutex<()>,
+}
+
+impl FsStore {
+    pub fn open(dir: PathBuf, max_resources: Option<usize>) -> Result<Self, String> {
+        fs::create_dir_all(&dir)
+            .map_err(|err| format!("Failed to create store dir {}: {:?}", dir.display(), err))?;
+        Ok(FsStore {
+            dir,
+            max_resources,
+            inserting: Mutex::new(()),
+        })
+    }
+
+    fn resource_count(&self) -> Result<usize, StoreError> {
+        let entries = fs::read_dir(&self.dir).map_err(|err| {
+            StoreError::Io(format!("Failed to list {}: {:?}", self.dir.display(), err))
+        })?;
+        Ok(entries.count())
+    }
+
+    /// Ids become file names, so only allow characters that cannot
+    /// escape `dir`
+    fn path_for(&self, id: &str) -> Result<PathBuf, StoreError> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(StoreError::InvalidId(id.to_string()));
+        }
+        Ok(self.dir.join(id))
+    }
+}
+
+impl ResourceStore for FsStore {
+    /// Fails with `StoreError::Full` at capacity, counting resources
+    /// left by earlier runs. Replacing an existing id is always allowed.
+    fn insert(&self, id: &str, resource: &[u8]) -> Result<(), StoreError> {
+        let path = self.path_for(id)?;
+        let _inserting = self.inserting.lock().unwrap();
+        if let Some(max) = self.max_resources {
+            if !path.exists() && self.resource_count()? >= max {
+                return Err(StoreError::Full);
+            }
+        }
+        fs::write(&path, resource)
+            .map_err(|err| StoreError::Io(format!("Failed to write {}: {:?}", path.display(), err)))
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        let path = self.path_for(id)?;
+        match fs::read(&path) {
+            Ok(resource) => Ok(Some(resource)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(StoreError::Io(format!(
+                "Failed to read {}: {:?}",
+                path.display(),
+                err
+            ))),
+        }
+    }
+
+    fn delete(&self, id: &str) -> Result<bool, StoreError> {
+        let path = self.path_for(id)?;
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(StoreError::Io(format!(
+                "Failed to delete {}: {:?}",
+                path.display(),
+                err
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("store-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn insert_get_delete(store: &dyn ResourceStore) {
+        store.insert("a1", b"first").unwrap();
+        store.insert("b2", b"second").unwrap();
+        assert_eq!(store.get("a1").unwrap(), Some(b"first".to_vec()));
+        assert_eq!(store.get("b2").unwrap(), Some(b"second".to_vec()));
+        assert_eq!(store.get("c3").unwrap(), None);
+
+        assert!(store.delete("a1").unwrap());
+        assert!(!store.delete("a1").unwrap());
+        assert_eq!(store.get("a1").unwrap(), None);
+        assert_eq!(store.get("b2").unwrap(), Some(b"second".to_vec()));
+    }
+
+    #[test]
+    fn memory_store_inserts_gets_and_deletes() {
+        insert_get_delete(&MemoryStore::new(None, StoreEviction::Reject));
+    }
+
+    #[test]
+    fn fs_store_inserts_gets_and_deletes() {
+        let dir = temp_dir("crud");
+        insert_get_delete(&FsStore::open(dir.clone(), None).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fs_store_keeps_resources_across_a_restart() {
+        let dir = temp_dir("restart");
+        FsStore::open(dir.clone(), None)
+            .unwrap()
+            .insert("kept", b"resource")
+            .unwrap();
+
+        let reopened = FsStore::open(dir.clone(), None).unwrap();
+        assert_eq!(reopened.get("kept").unwrap(), Some(b"resource".to_vec()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fs_store_rejects_ids_that_could_leave_its_dir() {
+        let dir = temp_dir("ids");
+        let store = FsStore::open(dir.clone(), None).unwrap();
+        assert_eq!(
+            store.insert("../escape", b"x"),
+            Err(StoreError::InvalidId("../escape".to_string()))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fs_store_enforces_max_resources_across_a_restart() {
+        let dir = temp_dir("max");
+        let store = FsStore::open(dir.clone(), Some(2)).unwrap();
+        store.insert("one", b"1").unwrap();
+        store.insert("two", b"2").unwrap();
+        assert_eq!(store.insert("three", b"3"), Err(StoreError::Full));
+        store.insert("two", b"2 again").unwrap();
+
+        let reopened = FsStore::open(dir.clone(), Some(2)).unwrap();
+        assert_eq!(reopened.insert("three", b"3"), Err(StoreError::Full));
+        assert_eq!(reopened.get("one").unwrap(), Some(b"1".to_vec()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn full_memory_store_rejects_without_evicting() {
+        let store = MemoryStore::new(Some(2), StoreEviction::Reject);
+        store.insert("one", b"1").unwrap();
+        store.insert("two", b"2").unwrap();
+
+        assert_eq!(store.insert("three", b"3"), Err(StoreError::Full));
+        assert_eq!(store.get("one").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(store.get("two").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(store.get("three").unwrap(), None);
+    }
+
+    #[test]
+    fn full_store_maps_to_insufficient_storage() {
+        let err = ApiError::from(StoreError::Full);
+        assert_eq!(err.status, Status::InsufficientStorage);
+        assert_eq!(err.error, "resource store is full");
+    }
+
+    #[test]
+    fn lru_store_evicts_the_least_recently_used_resource() {
+        let store = MemoryStore::new(Some(2), StoreEviction::Lru);
+        store.insert("one", b"1").unwrap();
+        store.insert("two", b"2").unwrap();
+        // Reading "one" leaves "two" as the least recently used
+        store.get("one").unwrap();
+
+        store.insert("three", b"3").unwrap();
+        assert_eq!(store.get("two").unwrap(), None);
+        assert_eq!(store.get("one").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(store.get("three").unwrap(), Some(b"3".to_vec()));
+    }
+
+    #[test]
+    fn replacing_a_resource_in_a_full_store_evicts_nothing() {
+        for eviction in [StoreEviction::Reject, StoreEviction::Lru] {
+            let store = MemoryStore::new(Some(2), eviction);
+            store.insert("one", b"1").unwrap();
+            store.insert("two", b"2").unwrap();
+
+            store.insert("one", b"1 again").unwrap();
+            assert_eq!(store.get("one").unwrap(), Some(b"1 again".to_vec()));
+            assert_eq!(store.get("two").unwrap(), Some(b"2".to_vec()));
+        }
+    }
+}
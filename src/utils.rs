@@ -1,16 +1,133 @@
-use log::error;
+use crate::command_parser::LogFormat;
+use log::{error, LevelFilter, Record};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::Serializer;
+use std::io::Write;
+use std::sync::OnceLock;
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+
+static EXIT_HANDLER: OnceLock<fn(i32) -> !> = OnceLock::new();
+
+/// Replaces `std::process::exit` as the way `ExitGracefully` terminates.
+/// Only the first call takes effect; returns false if a handler was already set.
+pub fn set_exit_handler(f: fn(i32) -> !) -> bool {
+    EXIT_HANDLER.set(f).is_ok()
+}
+
+/// Installs the process-wide logger. Must run before Rocket starts, which
+/// otherwise installs its own.
+pub fn init_logging(level: LevelFilter, format: LogFormat) -> Result<(), String> {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level);
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let ts = buf.timestamp().to_string();
+            writeln!(buf, "{}", json_log_line(record, &ts))
+        });
+    }
+    builder
+        .try_init()
+        .map_err(|err| format!("could not initialize logging: {}", err))
+}
+
+/// Renders `record` as a single-line JSON object for log aggregators
+pub fn json_log_line(record: &Record, ts: &str) -> String {
+    serde_json::json!({
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "msg": record.args().to_string(),
+        "ts": ts,
+    })
+    .to_string()
+}
+
+/// Compares secrets such as API keys without returning early, so
+/// response times don't reveal how much of a guess was right. Lengths are
+/// not secret; a mismatch is false straight away.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && bool::from(a.ct_eq(b))
+}
+
+/// Summarises key material for logs above `trace`: the length and the
+/// first 4 hex characters only
+pub fn redact(bytes: &[u8]) -> String {
+    let prefix = hex::encode(&bytes[..bytes.len().min(2)]);
+    format!("<{} bytes, {}…>", bytes.len(), prefix)
+}
+
+/// Longest the HTTP client subcommands wait to connect to the server
+pub const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest the HTTP client subcommands wait for a whole request
+pub const HTTP_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Client for the subcommands that call the HTTP API, with the connect
+/// and request timeouts set and `api_key` sent as `X-Api-Key`
+pub fn http_client(api_key: Option<&str>) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .connect_timeout(HTTP_CONNECT_TIMEOUT)
+        .timeout(HTTP_REQUEST_TIMEOUT)
+        .default_headers(api_key_headers(api_key)?)
+        .build()
+        .map_err(|err| format!("Failed to build HTTP client: {}", err))
+}
+
+/// Blocking counterpart of `http_client`
+pub fn blocking_http_client(api_key: Option<&str>) -> Result<reqwest::blocking::Client, String> {
+    reqwest::blocking::Client::builder()
+        .connect_timeout(HTTP_CONNECT_TIMEOUT)
+        .timeout(HTTP_REQUEST_TIMEOUT)
+        .default_headers(api_key_headers(api_key)?)
+        .build()
+        .map_err(|err| format!("Failed to build HTTP client: {}", err))
+}
+
+fn api_key_headers(api_key: Option<&str>) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    if let Some(api_key) = api_key {
+        let mut value = HeaderValue::from_str(api_key)
+            .map_err(|_| "API key is not a valid header value".to_string())?;
+        value.set_sensitive(true);
+        // Static header names must be lowercase; HTTP header names are case-insensitive
+        headers.insert("x-api-key", value);
+    }
+    Ok(headers)
+}
+
+/// Serializes a secret flag such as `--api-key` for `--print-config`:
+/// `"<redacted>"` when set, `null` otherwise. Unlike `redact`, no bytes
+/// of the secret are shown.
+pub fn serialize_redacted<S: Serializer>(
+    secret: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match secret {
+        Some(_) => serializer.serialize_str("<redacted>"),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn exit_with(code: i32) -> ! {
+    let handler = EXIT_HANDLER.get().copied().unwrap_or(std::process::exit);
+    handler(code)
+}
 
 pub trait ExitGracefully<T, E> {
     fn ok_or_exit(self, message: &str) -> T;
+    fn ok_or_exit_code(self, message: &str, code: i32) -> T;
 }
 
 impl<T, E: std::fmt::Debug> ExitGracefully<T, E> for Result<T, E> {
     fn ok_or_exit(self, message: &str) -> T {
+        self.ok_or_exit_code(message, 1)
+    }
+
+    fn ok_or_exit_code(self, message: &str, code: i32) -> T {
         match self {
             Ok(val) => val,
             Err(err) => {
                 error!("{:?}: {}", err, message);
-                std::process::exit(1);
+                exit_with(code);
             }
         }
     }
@@ -23,34 +140,450 @@ macro_rules! create_app {
             .about("Proxy Re Encryption Application")
             .setting(AppSettings::ArgRequiredElseHelp)
             .version(env!("CARGO_PKG_VERSION"))
+            .arg(
+                Arg::with_name("log-level")
+                    .long("log-level")
+                    .help("log level: trace, debug, info, warn or error (default info)")
+                    .takes_value(true)
+                    .possible_values(&["trace", "debug", "info", "warn", "error"])
+                    .global(true),
+            )
+            .arg(
+                Arg::with_name("log-format")
+                    .long("log-format")
+                    .help("log output format: text or json lines (default text)")
+                    .takes_value(true)
+                    .possible_values(&["text", "json"])
+                    .global(true),
+            )
             .subcommand(
                 SubCommand::with_name("server")
                     .about("Listen on a given port.")
+                    .arg(
+                        Arg::with_name("config")
+                            .long("config")
+                            .help("TOML file with default values for the flags; flags override it")
+                            .takes_value(true),
+                    )
                     .arg(
                         Arg::with_name("port")
                             .long("port")
-                            .help("port")
+                            .help("port, a range of ports to try in order (e.g. 8000-8010), or 0 for any free port")
                             .takes_value(true)
-                            .required(true),
+                            .required_unless("config"),
+                    )
+                    .arg(
+                        Arg::with_name("max-vsock-connections")
+                            .long("max-vsock-connections")
+                            .help("maximum number of connections handled at once")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("accept-deadline")
+                            .long("accept-deadline")
+                            .help("seconds to wait for a connection before exiting (for tests and CI)")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("recv-timeout-secs")
+                            .long("recv-timeout-secs")
+                            .help("seconds a connection may wait for data before it is dropped, 0 for no limit (default 30)")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("no-delay")
+                            .long("no-delay")
+                            .help("Disable send coalescing on accepted connections where the socket supports it"),
+                    )
+                    .arg(
+                        Arg::with_name("print-config")
+                            .long("print-config")
+                            .help("Print the effective configuration as JSON and exit"),
                     ),
             )
             .subcommand(
                 SubCommand::with_name("client")
                     .about("Connect to a given cid and port.")
+                    .arg(
+                        Arg::with_name("config")
+                            .long("config")
+                            .help("TOML file with default values for the flags; flags override it")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("http")
+                            .long("http")
+                            .help("Serve the HTTP API instead of requesting a key pair from the enclave"),
+                    )
+                    .arg(
+                        Arg::with_name("address")
+                            .long("address")
+                            .help("IP address the HTTP API binds to (default 127.0.0.1)")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("cors-origin")
+                            .long("cors-origin")
+                            .help("origin browsers may call the HTTP API from (default *)")
+                            .takes_value(true),
+                    )
                     .arg(
                         Arg::with_name("port")
                             .long("port")
                             .help("port")
                             .takes_value(true)
-                            .required(true),
+                            .required_unless("config"),
                     )
                     .arg(
                         Arg::with_name("cid")
                             .long("cid")
                             .help("cid")
                             .takes_value(true)
+                            .required_unless("config"),
+                    )
+                    .arg(
+                        Arg::with_name("include-freshness")
+                            .long("include-freshness")
+                            .help("Embed a timestamp and nonce in transformed objects"),
+                    )
+                    .arg(
+                        Arg::with_name("enable-debug-endpoints")
+                            .long("enable-debug-endpoints")
+                            .help("Mount diagnostic routes under /diag"),
+                    )
+                    .arg(
+                        Arg::with_name("audit-log")
+                            .long("audit-log")
+                            .help("file to append a JSON line to for every cryptographic operation")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("allow-transform-key-disclosure")
+                            .long("allow-transform-key-disclosure")
+                            .help("Let /fetch-content return the transform key when asked with ?include_transform_key=true"),
+                    )
+                    .arg(
+                        Arg::with_name("max-body-bytes")
+                            .long("max-body-bytes")
+                            .help("largest JSON request body accepted, in bytes (default 1048576)")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("api-key")
+                            .long("api-key")
+                            .help("require this key in the X-Api-Key header of key and content routes (default $PROXY_API_KEY)")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("connect-retries")
+                            .long("connect-retries")
+                            .help("vsock connection attempts before giving up (default 5)")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("connect-backoff-ms")
+                            .long("connect-backoff-ms")
+                            .help("milliseconds to wait before the first reconnect, doubled after each attempt (default 1000)")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("crypto-timeout")
+                            .long("crypto-timeout")
+                            .help("seconds a request waits for recrypt before answering 503")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("store-backend")
+                            .long("store-backend")
+                            .help("where uploaded resources are kept: memory (default) or fs")
+                            .possible_values(&["memory", "fs"])
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("store-dir")
+                            .long("store-dir")
+                            .help("directory for uploaded resources with --store-backend fs")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("max-resources")
+                            .long("max-resources")
+                            .help("maximum number of resources kept by the store")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("store-eviction")
+                            .long("store-eviction")
+                            .help("what a full store does with new uploads: reject (default) or lru (memory store only)")
+                            .possible_values(&["reject", "lru"])
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("print-config")
+                            .long("print-config")
+                            .help("Print the effective configuration as JSON and exit"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("bench")
+                    .about("Measure /fetch-content throughput and latency.")
+                    .arg(
+                        Arg::with_name("url")
+                            .long("url")
+                            .help("full URL of the /fetch-content route")
+                            .takes_value(true)
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("requests")
+                            .long("requests")
+                            .help("total number of requests to send")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("concurrency")
+                            .long("concurrency")
+                            .help("maximum number of requests in flight")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("api-key")
+                            .long("api-key")
+                            .help("key to send in the X-Api-Key header")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("print-config")
+                            .long("print-config")
+                            .help("Print the effective configuration as JSON and exit"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("fetch-signing-key")
+                    .about("Download the server's public signing key and cache it in a file.")
+                    .arg(
+                        Arg::with_name("url")
+                            .long("url")
+                            .help("full URL of the /signing-key route")
+                            .takes_value(true)
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .help("file to cache the signing key in")
+                            .takes_value(true)
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("verify-signing-key")
+                    .about("Check a transformed object was signed with the cached signing key.")
+                    .arg(
+                        Arg::with_name("key-file")
+                            .long("key-file")
+                            .help("file written by fetch-signing-key")
+                            .takes_value(true)
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("transformed-object")
+                            .long("transformed-object")
+                            .help("hex-encoded transformed_object from /fetch-content")
+                            .takes_value(true)
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("convert-keys")
+                    .about("Convert a keys JSON file between byte-array and hex formats.")
+                    .arg(
+                        Arg::with_name("in")
+                            .long("in")
+                            .help("keys file in either format")
+                            .takes_value(true)
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("out")
+                            .long("out")
+                            .help("file to write the converted keys to")
+                            .takes_value(true)
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::with_name("to")
+                            .long("to")
+                            .help("output format")
+                            .possible_values(&["hex", "bytes"])
+                            .takes_value(true)
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("encrypt")
+                    .about("Encrypt and transform a /fetch-content payload without starting the HTTP server.")
+                    .arg(
+                        Arg::with_name("in")
+                            .long("in")
+                            .help("payload JSON file, stdin if omitted")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("plaintext-file")
+                            .long("plaintext-file")
+                            .help("file to encrypt in plaintext-sized chunks instead of the payload's resource")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("transform-key-file")
+                            .long("transform-key-file")
+                            .help("transform key written by the transform-key command, used instead of the payload's private key")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("dump-proto")
+                            .long("dump-proto")
+                            .help("file to also write the raw TransformObject protobuf to")
+                            .takes_value(true)
+                            .conflicts_with("plaintext-file"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("transform-key")
+                    .about("Generate a transform key from a /transform-key payload and write it to a file.")
+                    .arg(
+                        Arg::with_name("in")
+                            .long("in")
+                            .help("payload JSON file, stdin if omitted")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("out")
+                            .long("out")
+                            .help("file to write the transform key to as JSON")
+                            .takes_value(true)
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("keygen")
+                    .about("Generate a recrypt key pair without starting the HTTP server.")
+                    .arg(
+                        Arg::with_name("out")
+                            .long("out")
+                            .help("file to write the keys to as JSON, stdout if omitted")
+                            .takes_value(true),
+                    )
+                    .arg(
+                        Arg::with_name("seed")
+                            .long("seed")
+                            .help("64 hex characters to derive the key pair from deterministically")
+                            .takes_value(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("ed25519-keygen")
+                    .about("Generate an ed25519 key pair and write it to a file.")
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .help("file to write the hex-encoded key pair to, as JSON")
+                            .takes_value(true)
+                            .required(true),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("completions")
+                    .about("Print a shell completion script to stdout.")
+                    .arg(
+                        Arg::with_name("shell")
+                            .long("shell")
+                            .help("shell to generate completions for")
+                            .takes_value(true)
+                            .possible_values(&clap::Shell::variants())
                             .required(true),
                     ),
             )
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_key_is_sent_as_sensitive_header() {
+        let headers = api_key_headers(Some("secret")).unwrap();
+        let value = headers.get("x-api-key").unwrap();
+        assert_eq!(value, "secret");
+        assert!(value.is_sensitive());
+        assert!(api_key_headers(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn api_key_with_newline_is_rejected() {
+        assert!(api_key_headers(Some("secret\n")).is_err());
+        assert!(http_client(Some("secret\n")).is_err());
+    }
+
+    #[test]
+    fn ct_eq_compares_contents_and_length() {
+        assert!(ct_eq(b"secret-key", b"secret-key"));
+        assert!(!ct_eq(b"secret-key", b"secret-kez"));
+        assert!(!ct_eq(b"secret-key", b"secret"));
+        assert!(ct_eq(b"", b""));
+    }
+
+    #[test]
+    fn redact_shows_only_the_length_and_a_short_prefix() {
+        let key = [0xde, 0xad, 0xbe, 0xef, 0x01, 0x02];
+        assert_eq!(redact(&key), "<6 bytes, dead…>");
+        assert_eq!(redact(&[0xab]), "<1 bytes, ab…>");
+        assert_eq!(redact(&[]), "<0 bytes, …>");
+    }
+
+    #[test]
+    fn json_log_line_is_valid_json() {
+        let line = json_log_line(
+            &Record::builder()
+                .args(format_args!("quoted \"value\"\nand a newline"))
+                .level(log::Level::Warn)
+                .target("proxy_reencryption")
+                .build(),
+            "2026-10-17T12:00:00Z",
+        );
+
+        assert!(!line.contains('\n'));
+        let parsed: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed["level"], "WARN");
+        assert_eq!(parsed["target"], "proxy_reencryption");
+        assert_eq!(parsed["msg"], "quoted \"value\"\nand a newline");
+        assert_eq!(parsed["ts"], "2026-10-17T12:00:00Z");
+    }
+
+    #[test]
+    fn ok_or_exit_code_returns_the_ok_value() {
+        let parsed: Result<u32, String> = Ok(5005);
+        assert_eq!(parsed.ok_or_exit_code("unreachable", 2), 5005);
+        assert_eq!(Ok::<_, String>("value").ok_or_exit("unreachable"), "value");
+    }
+
+    fn panicking_exit(code: i32) -> ! {
+        panic!("exited with code {}", code)
+    }
+
+    #[test]
+    fn ok_or_exit_routes_errors_through_the_exit_handler() {
+        set_exit_handler(panicking_exit);
+
+        let result = std::panic::catch_unwind(|| {
+            Err::<(), _>("bad port").ok_or_exit_code("Invalid arguments", 2)
+        });
+        let payload = result.unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<String>().unwrap(),
+            "exited with code 2"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use proxy_reencyption_enclave_app::{
+    hardcoded_plaintext, transform_payload, CryptoParams, Payload,
+};
+use recrypt::api::{CryptoOps, Ed25519Ops, KeyGenOps, PrivateKey, PublicKey, Recrypt};
+
+fn payload_for(
+    owner: &(PrivateKey, PublicKey),
+    delegatee_public_key: &PublicKey,
+    resource: &[u8],
+) -> Payload {
+    let (owner_x, owner_y) = owner.1.bytes_x_y();
+    let (delegatee_x, delegatee_y) = delegatee_public_key.bytes_x_y();
+    serde_json::from_value(serde_json::json!({
+        "initial_private_key": hex::encode(owner.0.bytes()),
+        "initial_public_key_x": hex::encode(owner_x),
+        "initial_public_key_y": hex::encode(owner_y),
+        "delegatee_public_key_x": hex::encode(delegatee_x),
+        "delegatee_public_key_y": hex::encode(delegatee_y),
+        "resource": hex::encode(resource),
+    }))
+    .unwrap()
+}
+
+#[test]
+fn transformed_demo_plaintext_decrypts_with_delegatee_key() {
+    let recrypt = Recrypt::new();
+    let params = CryptoParams::detect().unwrap();
+    let owner = recrypt.generate_key_pair().unwrap();
+    let (delegatee_private_key, delegatee_public_key) = recrypt.generate_key_pair().unwrap();
+    let signing_keypair = recrypt.generate_ed25519_key_pair();
+
+    let payload = payload_for(&owner, &delegatee_public_key, &[]);
+    let transformed = transform_payload(&recrypt, &payload, &params, &signing_keypair).unwrap();
+
+    let decrypted = recrypt
+        .decrypt(transformed, &delegatee_private_key)
+        .unwrap();
+    assert_eq!(
+        decrypted.bytes()[..],
+        hardcoded_plaintext(&params).unwrap().bytes()[..]
+    );
+}
+
+#[test]
+fn transformed_resource_decrypts_with_delegatee_key() {
+    let recrypt = Recrypt::new();
+    let params = CryptoParams::detect().unwrap();
+    let owner = recrypt.generate_key_pair().unwrap();
+    let (delegatee_private_key, delegatee_public_key) = recrypt.generate_key_pair().unwrap();
+    let signing_keypair = recrypt.generate_ed25519_key_pair();
+    let resource = recrypt.gen_plaintext();
+
+    let payload = payload_for(&owner, &delegatee_public_key, resource.bytes());
+    let transformed = transform_payload(&recrypt, &payload, &params, &signing_keypair).unwrap();
+
+    let decrypted = recrypt
+        .decrypt(transformed, &delegatee_private_key)
+        .unwrap();
+    assert_eq!(decrypted.bytes()[..], resource.bytes()[..]);
+}